@@ -0,0 +1,148 @@
+use crate::body::SharedBody;
+
+/// Stable reference to a body in the world. The generation makes sure that a handle to a removed
+/// body does not silently start pointing at whatever body reuses its slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BodyHandle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot {
+    generation: u32,
+    body: Option<SharedBody>,
+}
+
+/// Generational arena the world keeps its bodies in. Slots of removed bodies are reused.
+#[derive(Default)]
+pub struct BodyArena {
+    slots: Vec<Slot>,
+    free: Vec<u32>,
+}
+
+impl BodyArena {
+    pub fn insert(&mut self, body: SharedBody) -> BodyHandle {
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.body = Some(body);
+
+                BodyHandle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    body: Some(body),
+                });
+
+                BodyHandle {
+                    index: (self.slots.len() - 1) as u32,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<SharedBody> {
+        let slot = self.slots.get_mut(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        let body = slot.body.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+
+        Some(body)
+    }
+
+    pub fn get(&self, handle: BodyHandle) -> Option<&SharedBody> {
+        let slot = self.slots.get(handle.index as usize)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        slot.body.as_ref()
+    }
+
+    /// Iterates over the live bodies in ascending handle order.
+    pub fn iter(&self) -> impl Iterator<Item = (BodyHandle, &SharedBody)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.body.as_ref().map(|body| {
+                (
+                    BodyHandle {
+                        index: index as u32,
+                        generation: slot.generation,
+                    },
+                    body,
+                )
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use nalgebra_glm::Vec2;
+
+    use super::*;
+    use crate::body::Body;
+
+    fn body() -> SharedBody {
+        Body::test_square(Vec2::zeros()).into_shared()
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut arena = BodyArena::default();
+        let (a, b) = (body(), body());
+        let handle_a = arena.insert(a.clone());
+        let handle_b = arena.insert(b.clone());
+
+        assert_ne!(handle_a, handle_b);
+        assert!(Arc::ptr_eq(arena.get(handle_a).unwrap(), &a));
+        assert!(Arc::ptr_eq(arena.get(handle_b).unwrap(), &b));
+    }
+
+    #[test]
+    fn removed_slots_are_reused_with_a_new_generation() {
+        let mut arena = BodyArena::default();
+        let removed = arena.insert(body());
+        arena.remove(removed).unwrap();
+
+        let reused = arena.insert(body());
+        assert_eq!(reused.index, removed.index);
+        assert_ne!(reused.generation, removed.generation);
+        assert_ne!(reused, removed);
+    }
+
+    #[test]
+    fn stale_handles_are_rejected() {
+        let mut arena = BodyArena::default();
+        let stale = arena.insert(body());
+        arena.remove(stale).unwrap();
+        let reused = arena.insert(body());
+
+        assert!(arena.get(stale).is_none());
+        assert!(arena.remove(stale).is_none());
+        // The body that took over the slot is left alone
+        assert!(arena.get(reused).is_some());
+    }
+
+    #[test]
+    fn iter_skips_removed_bodies() {
+        let mut arena = BodyArena::default();
+        let a = arena.insert(body());
+        let b = arena.insert(body());
+        let c = arena.insert(body());
+        arena.remove(b);
+
+        let handles: Vec<BodyHandle> = arena.iter().map(|(handle, _)| handle).collect();
+        assert_eq!(handles, vec![a, c]);
+    }
+}
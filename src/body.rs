@@ -1,28 +1,186 @@
 use std::fs;
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-use nalgebra_glm::{rotation2d, scaling2d, translation2d, vec2};
+use nalgebra_glm::{inverse, rotation2d, scaling2d, translation2d};
 use nalgebra_glm::{Mat3x3, Vec2, Vec3};
 use raylib::color::Color;
 use raylib::prelude::{RaylibDrawHandle, RaylibMode2D};
 use serde::{Deserialize, Serialize};
 
+use crate::collision_filter::CollisionFilter;
 use crate::color;
+use crate::integrator::State;
+use crate::math::{angular_to_tangent, cross};
 use crate::polygon::Polygon;
 
+/// Bodies are shared between the engine and the constraints acting on them. The lock lets the
+/// solver work on independent islands from different threads.
+pub type SharedBody = Arc<RwLock<Body>>;
+
+/// Reads both bodies, for preparing a constraint between them.
+pub fn read_pair<'a>(
+    a: &'a SharedBody,
+    b: &'a SharedBody,
+) -> (RwLockReadGuard<'a, Body>, RwLockReadGuard<'a, Body>) {
+    (
+        a.read().expect("Body lock was poisoned."),
+        b.read().expect("Body lock was poisoned."),
+    )
+}
+
+/// Joints lock both of their bodies, so one that connects a body to itself would deadlock the
+/// solver.
+pub fn assert_distinct(a: &SharedBody, b: &SharedBody) {
+    assert!(
+        !Arc::ptr_eq(a, b),
+        "A joint cannot connect a body to itself."
+    );
+}
+
+/// Linear and angular velocity of a body.
+pub type Velocity = (Vec2, f32);
+
+/// A body as the solver sees it. Only dynamic bodies are locked for writing, the rest are never
+/// pushed so their velocity is copied once when the constraint is prepared. That way islands that
+/// all rest on or hang from the same ground do not queue up on its lock.
+pub enum SolverBody<'a> {
+    Dynamic(RwLockWriteGuard<'a, Body>),
+    Fixed(Velocity),
+}
+
+impl<'a> SolverBody<'a> {
+    /// `fixed_velocity` is what `Body::fixed_velocity` gave when the constraint was prepared.
+    pub fn lock(body: &'a SharedBody, fixed_velocity: Option<Velocity>) -> Self {
+        match fixed_velocity {
+            Some(velocity) => SolverBody::Fixed(velocity),
+            None => SolverBody::Dynamic(body.write().expect("Body lock was poisoned.")),
+        }
+    }
+
+    /// Locks the bodies of a constraint. Dynamic bodies are always locked in address order so
+    /// that two threads locking the same pair cannot deadlock.
+    pub fn lock_pair(
+        a: &'a SharedBody,
+        b: &'a SharedBody,
+        fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    ) -> (Self, Self) {
+        if Arc::as_ptr(a) <= Arc::as_ptr(b) {
+            let a = Self::lock(a, fixed_velocities.0);
+            let b = Self::lock(b, fixed_velocities.1);
+            (a, b)
+        } else {
+            let b = Self::lock(b, fixed_velocities.1);
+            let a = Self::lock(a, fixed_velocities.0);
+            (a, b)
+        }
+    }
+
+    pub fn velocity(&self) -> Vec2 {
+        match self {
+            SolverBody::Dynamic(body) => body.velocity(),
+            SolverBody::Fixed((velocity, _)) => *velocity,
+        }
+    }
+
+    pub fn angular_velocity(&self) -> f32 {
+        match self {
+            SolverBody::Dynamic(body) => body.angular_velocity(),
+            SolverBody::Fixed((_, angular_velocity)) => *angular_velocity,
+        }
+    }
+
+    pub fn velocity_at(&self, offset: Vec2) -> Vec2 {
+        self.velocity() + angular_to_tangent(self.angular_velocity(), offset)
+    }
+
+    /// Fixed bodies act as if their mass was infinite.
+    pub fn inv_mass(&self) -> f32 {
+        match self {
+            SolverBody::Dynamic(body) => body.inv_mass(),
+            SolverBody::Fixed(_) => 0.,
+        }
+    }
+
+    pub fn inv_inertia(&self) -> f32 {
+        match self {
+            SolverBody::Dynamic(body) => body.inv_inertia(),
+            SolverBody::Fixed(_) => 0.,
+        }
+    }
+
+    pub fn apply_impulse(&mut self, impulse: Vec2) {
+        if let SolverBody::Dynamic(body) = self {
+            body.apply_impulse(impulse);
+        }
+    }
+
+    pub fn apply_angular_impulse(&mut self, impulse: f32) {
+        if let SolverBody::Dynamic(body) = self {
+            body.apply_angular_impulse(impulse);
+        }
+    }
+
+    pub fn apply_impulse_at(&mut self, impulse: Vec2, offset: Vec2) {
+        if let SolverBody::Dynamic(body) = self {
+            body.apply_impulse_at(impulse, offset);
+        }
+    }
+}
+
+/// How a body takes part in the simulation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BodyType {
+    /// Never moves
+    Static,
+    /// Moves by the velocity set on it, pushes dynamic bodies without being pushed back
+    Kinematic,
+    /// Moved by forces, impulses and collisions
+    #[default]
+    Dynamic,
+}
+
+/// How quickly the linear and angular velocity of a body die down, as a rate per second.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Damping {
+    #[serde(default)]
+    pub linear: f32,
+    #[serde(default)]
+    pub angular: f32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Body {
     // Non-Physics Variables
+    /// Used by the scene file to refer to this body
+    #[serde(default)]
+    name: Option<String>,
     pub position: Vec2,
     pub rotation: f32,
     scale: Vec2,
     collider_file: String,
     // Body Properties (Probably turn the f32s into some sort of properties struct)
     collider: Option<Polygon>,
+    #[serde(default)]
+    filter: CollisionFilter,
+    /// Sensors only report overlaps, they are never pushed and never push anything
+    #[serde(default)]
+    sensor: bool,
     mass: f32,
     inertia: f32,
     restitution: f32,
     friction: f32,
+    /// Falls back to the damping of the world when left out
+    #[serde(default)]
+    linear_damping: Option<f32>,
+    #[serde(default)]
+    angular_damping: Option<f32>,
+    /// How strongly gravity, uniform or from attractors, pulls on the body
+    #[serde(default = "default_gravity_scale")]
+    gravity_scale: f32,
     #[serde(default)]
+    body_type: BodyType,
+    /// Older scene files mark static bodies with this instead of `body_type`
+    #[serde(default, skip_serializing)]
     is_static: bool,
     // Linear Runtime Physics Variables
     #[serde(default)]
@@ -38,34 +196,131 @@ pub struct Body {
     moment: f32,
 }
 
+fn default_gravity_scale() -> f32 {
+    1.
+}
+
 impl Body {
-    pub fn check_collision(&self, other: &Body, dt: f32) -> Option<(bool, Vec2, f32)> {
-        let collider = self.collider.as_ref()?;
-        let other_collider = other.collider.as_ref()?;
+    /// Dynamic body made in code rather than read from a scene file. The collider still has to be
+    /// constructed from its file.
+    pub fn new(
+        collider_file: &str,
+        position: Vec2,
+        rotation: f32,
+        scale: Vec2,
+        mass: f32,
+        inertia: f32,
+    ) -> Self {
+        Self {
+            name: None,
+            position,
+            rotation,
+            scale,
+            collider_file: collider_file.to_string(),
+            collider: None,
+            filter: CollisionFilter::default(),
+            sensor: false,
+            mass,
+            inertia,
+            restitution: 0.,
+            friction: 1.,
+            linear_damping: None,
+            angular_damping: None,
+            gravity_scale: 1.,
+            body_type: BodyType::Dynamic,
+            is_static: false,
+            velocity: Vec2::default(),
+            net_force: Vec2::default(),
+            center_of_gravity: Vec2::default(),
+            angular_velocity: 0.,
+            moment: 0.,
+        }
+    }
 
-        let collider = collider.get_in_world(&self.get_transform());
-        let other_collider = other_collider.get_in_world(&other.get_transform());
+    /// Moves a kinematic body along with the velocity it was given, nothing acts on it. Dynamic
+    /// bodies are moved by the integrator of the world instead.
+    pub fn integrate(&mut self, dt: f32) {
+        if self.body_type() == BodyType::Kinematic {
+            self.position += self.velocity * dt;
+            self.rotation += self.angular_velocity * dt;
+        }
 
-        collider.check_collision(&other_collider, dt)
+        self.clear_forces();
     }
 
-    pub fn integrate(&mut self, dt: f32) {
-        if self.is_static {
+    /// Velocity half of a symplectic Euler step, for the substeps of a sub-stepped world.
+    pub fn integrate_velocity(&mut self, dt: f32, gravity: Vec2, default_damping: Damping) {
+        if !self.is_dynamic() {
             return;
         }
 
-        // TODO: Make gravity changeable
-        let gravity = vec2(0., 9.81) * 0.;
-
-        let acceleration = (1. / self.mass) * self.net_force + gravity;
+        let (acceleration, angular_acceleration) = self.acceleration(gravity);
         self.velocity += acceleration * dt;
-        self.position += self.velocity * dt;
-
-        let angular_acceleration = self.moment / self.inertia;
         self.angular_velocity += angular_acceleration * dt;
+        self.damp(dt, default_damping);
+    }
+
+    /// Position half of a symplectic Euler step, moves kinematic bodies too.
+    pub fn integrate_position(&mut self, dt: f32) {
+        if self.body_type() == BodyType::Static {
+            return;
+        }
+
+        self.position += self.velocity * dt;
         self.rotation += self.angular_velocity * dt;
     }
 
+    /// Acceleration from the applied forces and the uniform `gravity`, linear and angular.
+    pub fn acceleration(&self, gravity: Vec2) -> (Vec2, f32) {
+        (
+            (1. / self.mass) * self.net_force + self.gravity_scale * gravity,
+            self.moment / self.inertia,
+        )
+    }
+
+    /// Force and torque applied to the body so far.
+    pub fn applied_forces(&self) -> (Vec2, f32) {
+        (self.net_force, self.moment)
+    }
+
+    /// Forces only act during the step they were applied in.
+    pub fn clear_forces(&mut self) {
+        self.net_force = Vec2::default();
+        self.moment = 0.;
+    }
+
+    /// Lets the velocities decay over `dt`. `default_damping` is used for whatever damping the
+    /// body does not set itself.
+    pub fn damp(&mut self, dt: f32, default_damping: Damping) {
+        // Exponential decay, so the damping is the same no matter how the time is split into steps
+        let linear_damping = self.linear_damping.unwrap_or(default_damping.linear);
+        let angular_damping = self.angular_damping.unwrap_or(default_damping.angular);
+        self.velocity *= (-linear_damping * dt).exp();
+        self.angular_velocity *= (-angular_damping * dt).exp();
+    }
+
+    pub fn state(&self) -> State {
+        State {
+            position: self.position,
+            rotation: self.rotation,
+            velocity: self.velocity,
+            angular_velocity: self.angular_velocity,
+        }
+    }
+
+    pub fn set_state(&mut self, state: State) {
+        self.position = state.position;
+        self.rotation = state.rotation;
+        self.velocity = state.velocity;
+        self.angular_velocity = state.angular_velocity;
+    }
+
+    /// Kinetic energy, linear and angular together.
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass() * self.velocity.magnitude_squared()
+            + 0.5 * self.inertia() * self.angular_velocity * self.angular_velocity
+    }
+
     pub fn construct_collider(&mut self) {
         let collider_file =
             fs::read_to_string("assets/colliders/".to_string() + &self.collider_file)
@@ -93,8 +348,36 @@ impl Body {
         }
     }
 
-    pub fn is_static(&self) -> bool {
-        self.is_static
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn body_type(&self) -> BodyType {
+        if self.is_static {
+            BodyType::Static
+        } else {
+            self.body_type
+        }
+    }
+
+    /// Only dynamic bodies respond to the solver, the rest act as if their mass was infinite.
+    pub fn is_dynamic(&self) -> bool {
+        self.body_type() == BodyType::Dynamic
+    }
+
+    /// Pushes the body through its center of gravity during the next step.
+    pub fn apply_force(&mut self, force: Vec2) {
+        self.net_force += force;
+    }
+
+    /// Pushes the body at `point`, which is in world space, during the next step.
+    pub fn apply_force_at_point(&mut self, force: Vec2, point: Vec2) {
+        self.apply_force(force);
+        self.apply_torque(cross(point - self.center_of_gravity(), force));
+    }
+
+    pub fn apply_torque(&mut self, torque: f32) {
+        self.moment += torque;
     }
 
     pub fn apply_impulse(&mut self, impulse: Vec2) {
@@ -105,6 +388,27 @@ impl Body {
         self.angular_velocity += impulse * self.inv_inertia();
     }
 
+    /// Applies an impulse at `offset` from the center of gravity.
+    pub fn apply_impulse_at(&mut self, impulse: Vec2, offset: Vec2) {
+        self.apply_impulse(impulse);
+        self.apply_angular_impulse(cross(offset, impulse));
+    }
+
+    /// Velocity of the point at `offset` from the center of gravity.
+    pub fn velocity_at(&self, offset: Vec2) -> Vec2 {
+        self.velocity + angular_to_tangent(self.angular_velocity, offset)
+    }
+
+    /// Takes a point in the same local space as the collider points into world space.
+    pub fn local_to_world(&self, point: Vec2) -> Vec2 {
+        (self.get_transform() * Vec3::new(point.x, point.y, 1.)).xy()
+    }
+
+    /// Inverse of [`Body::local_to_world`].
+    pub fn world_to_local(&self, point: Vec2) -> Vec2 {
+        (inverse(&self.get_transform()) * Vec3::new(point.x, point.y, 1.)).xy()
+    }
+
     pub fn collider(&self) -> Option<&Polygon> {
         self.collider.as_ref()
     }
@@ -113,6 +417,26 @@ impl Body {
         &mut self.collider
     }
 
+    pub fn gravity_scale(&self) -> f32 {
+        self.gravity_scale
+    }
+
+    pub fn restitution(&self) -> f32 {
+        self.restitution
+    }
+
+    pub fn friction(&self) -> f32 {
+        self.friction
+    }
+
+    pub fn filter(&self) -> CollisionFilter {
+        self.filter
+    }
+
+    pub fn is_sensor(&self) -> bool {
+        self.sensor
+    }
+
     pub fn collider_in_world(&self) -> Option<Polygon> {
         self.collider()
             .map(|collider| collider.get_in_world(&self.get_transform()))
@@ -134,7 +458,7 @@ impl Body {
     }
 
     pub fn mass(&self) -> f32 {
-        if !self.is_static() {
+        if self.is_dynamic() {
             self.mass
         } else {
             0.
@@ -142,15 +466,20 @@ impl Body {
     }
 
     pub fn inertia(&self) -> f32 {
-        if !self.is_static() {
+        if self.is_dynamic() {
             self.inertia
         } else {
             0.
         }
     }
 
+    /// Velocity of a body the solver does not move, `None` for dynamic bodies.
+    pub fn fixed_velocity(&self) -> Option<Velocity> {
+        (!self.is_dynamic()).then(|| (self.velocity, self.angular_velocity))
+    }
+
     pub fn inv_mass(&self) -> f32 {
-        if !self.is_static() {
+        if self.is_dynamic() {
             1. / self.mass
         } else {
             0.
@@ -158,10 +487,22 @@ impl Body {
     }
 
     pub fn inv_inertia(&self) -> f32 {
-        if !self.is_static() {
+        if self.is_dynamic() {
             1. / self.inertia
         } else {
             0.
         }
     }
 }
+
+#[cfg(test)]
+impl Body {
+    /// Unit square with a mass and inertia of one, the body tests are built around.
+    pub fn test_square(position: Vec2) -> Self {
+        Self::new("StdSquare.json", position, 0., Vec2::new(1., 1.), 1., 1.)
+    }
+
+    pub fn into_shared(self) -> SharedBody {
+        Arc::new(RwLock::new(self))
+    }
+}
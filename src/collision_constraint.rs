@@ -1,128 +1,486 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{any::Any, sync::Arc};
 
 use nalgebra_glm::Vec2;
 
 use crate::{
-    body::Body,
-    constraints::Constraint,
+    body::{Body, SharedBody, SolverBody, Velocity},
+    constraints::{Constraint, Softness},
     contact_point::{ContactID, ContactPoint},
+    debug_draw::DebugShape,
+    math::rotate,
+    polygon::Polygon,
 };
 
+/// Relative speed along the normal below which contacts do not bounce, so resting bodies settle
+const RESTITUTION_THRESHOLD: f32 = 1.;
+
+/// Penetration that is left alone, so resting contacts do not jitter in and out of touching
+const ALLOWED_PENETRATION: f32 = 0.01;
+
+// Soft contacts of sub-stepped worlds, like in Box2D v3
+const CONTACT_FREQUENCY: f32 = 30.;
+const CONTACT_DAMPING_RATIO: f32 = 10.;
+/// Fastest speed overlapping bodies are pushed apart at
+const CONTACT_PUSH_SPEED: f32 = 100.;
+
 pub struct CollisionConstraint {
     manifold: Vec<ContactPoint>,
 
-    incident_body: Rc<RefCell<Body>>,
-    reference_body: Rc<RefCell<Body>>,
+    incident_body: SharedBody,
+    reference_body: SharedBody,
+    /// Velocities of the incident and reference body, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+
+    // Set by pre-solve hooks, only for the current step
+    enabled: bool,
+    friction: Option<f32>,
+    restitution: Option<f32>,
+    tangent_speed: f32,
+
+    // Solver data (recomputed in pre_solve)
+    mixed_friction: f32,
+    mixed_restitution: f32,
+    /// Only set while the world is sub-stepped
+    sub_step: Option<SubStep>,
+}
+
+/// Solver settings of a sub-stepped world, recorded on the first substep of a step.
+#[derive(Debug, Clone, Copy)]
+struct SubStep {
+    softness: Softness,
+    inv_h: f32,
+    /// Relaxing solves without the bias
+    use_bias: bool,
+    /// Rotations of the incident and reference body at the start of the step
+    start_rotations: (f32, f32),
 }
 
 impl Constraint for CollisionConstraint {
     // HACK: Left off here, going over the math to find the mistake related to rotations
     fn pre_solve(&mut self, inv_dt: f32) {
-        let allowed_penetration = 0.01;
         let bias_factor = 0.2;
+        self.sub_step = None;
+
+        if !self.enabled {
+            self.reset_impulses();
+            return;
+        }
 
-        let mut incident_body = self.incident_body.borrow_mut();
-        let mut reference_body = self.reference_body.borrow_mut();
+        {
+            let incident_body = self.incident_body.read().expect("Body lock was poisoned.");
+            let reference_body = self.reference_body.read().expect("Body lock was poisoned.");
 
-        for contact in self.manifold.iter_mut() {
-            contact.set_to_incident(contact.point() - incident_body.center_of_gravity());
-            contact.set_to_reference(contact.point() - reference_body.center_of_gravity());
-
-            let net_inv_mass = incident_body.inv_mass() + reference_body.inv_mass();
-
-            // Effective mass (mass that affects the linear push done to the contact point)
-            let incident_normal_mass = contact.to_incident().dot(&contact.normal());
-            let reference_normal_mass = contact.to_reference().dot(&contact.normal());
-
-            let net_normal_mass = net_inv_mass
-                + incident_body.inv_inertia()
-                    * (contact.to_incident().norm_squared()
-                        - (incident_normal_mass * incident_normal_mass))
-                + reference_body.inv_inertia()
-                    * (contact.to_reference().norm_squared()
-                        - (reference_normal_mass * reference_normal_mass));
-
-            contact.set_effective_mass(1. / net_normal_mass);
-
-            // Tangent mass
-            let tangent = Vec2::new(-contact.normal().y, contact.normal().x);
-            let incident_tangent_mass = contact.to_incident().dot(&tangent);
-            let reference_tangent_mass = contact.to_reference().dot(&tangent);
-
-            let net_tangent_mass = net_inv_mass
-                + incident_body.inv_inertia()
-                    * (contact.to_incident().norm_squared()
-                        - (incident_tangent_mass * incident_tangent_mass))
-                + reference_body.inv_inertia()
-                    * (contact.to_reference().norm_squared()
-                        - (reference_tangent_mass * reference_tangent_mass));
-
-            contact.set_tangent_mass(1. / net_tangent_mass);
-
-            // Setting the bias
-            contact.set_bias(
-                -bias_factor * inv_dt * (contact.penetration() + allowed_penetration).min(0.),
+            self.fixed_velocities = (
+                incident_body.fixed_velocity(),
+                reference_body.fixed_velocity(),
+            );
+            (self.mixed_friction, self.mixed_restitution) = Self::mix_materials(
+                self.friction,
+                self.restitution,
+                &incident_body,
+                &reference_body,
             );
 
-            // Applying accumulated impulses
-            contact.apply_accumulated_impulses(&mut incident_body, &mut reference_body);
+            for contact in self.manifold.iter_mut() {
+                if !contact.is_enabled() {
+                    contact.set_accumulated_normal_impulse(0.);
+                    contact.set_accumulated_tangent_impulse(0.);
+                    continue;
+                }
+
+                Self::prepare_contact(contact, &incident_body, &reference_body);
+
+                // Setting the bias, bouncing takes over from the position correction if it is
+                // faster
+                let position_bias =
+                    -bias_factor * inv_dt * (contact.penetration() + ALLOWED_PENETRATION).min(0.);
+
+                let restitution_bias = if contact.normal_speed() < -RESTITUTION_THRESHOLD {
+                    -self.mixed_restitution * contact.normal_speed()
+                } else {
+                    0.
+                };
+
+                contact.set_bias(position_bias.max(restitution_bias));
+            }
         }
+
+        // Applying accumulated impulses, only once every bias has seen the velocities before them
+        self.warm_start();
     }
 
     fn solve(&mut self) {
-        let mut incident_body = self.incident_body.borrow_mut();
-        let mut reference_body = self.reference_body.borrow_mut();
-
-        let angular_to_tangent = |a: f32, b: Vec2| -> Vec2 { Vec2::new(-a * b.y, a * b.x) };
+        if !self.enabled {
+            return;
+        }
 
-        let cross = |a: Vec2, b: Vec2| -> f32 { a.x * b.y - a.y * b.x };
+        let (mut incident_body, mut reference_body) = SolverBody::lock_pair(
+            &self.incident_body,
+            &self.reference_body,
+            self.fixed_velocities,
+        );
+        let sub_step = self.sub_step;
 
         for contact in self.manifold.iter_mut() {
-            let relative_velocity = incident_body.velocity()
-                + angular_to_tangent(incident_body.angular_velocity(), contact.to_incident())
-                - reference_body.velocity()
-                - angular_to_tangent(reference_body.angular_velocity(), contact.to_reference());
+            if !contact.is_enabled() {
+                continue;
+            }
+
+            // Friction, limited by how hard the bodies are being pressed together
+            {
+                let tangent = Vec2::new(-contact.normal().y, contact.normal().x);
+                let relative_velocity = incident_body.velocity_at(contact.to_incident())
+                    - reference_body.velocity_at(contact.to_reference());
 
+                let tangent_impulse = -contact.tangent_mass()
+                    * (relative_velocity.dot(&tangent) - self.tangent_speed);
+
+                let max_friction = self.mixed_friction * contact.accumulated_normal_impulse();
+                let previous = contact.accumulated_tangent_impulse();
+                let accumulated = (previous + tangent_impulse).clamp(-max_friction, max_friction);
+                contact.set_accumulated_tangent_impulse(accumulated);
+
+                let to_apply = (accumulated - previous) * tangent;
+                incident_body.apply_impulse_at(to_apply, contact.to_incident());
+                reference_body.apply_impulse_at(-to_apply, contact.to_reference());
+            }
+
+            let relative_velocity = incident_body.velocity_at(contact.to_incident())
+                - reference_body.velocity_at(contact.to_reference());
+
+            let (bias, mass_scale, impulse_scale) = Self::normal_bias(sub_step, contact);
             let normal_impulse = contact.effective_mass()
-                * (-relative_velocity.dot(&contact.normal()) + contact.bias());
+                * mass_scale
+                * (-relative_velocity.dot(&contact.normal()) + bias)
+                - impulse_scale * contact.accumulated_normal_impulse();
 
             // Clamping
-            let to_apply = (contact.accumulated_normal_impulse() + normal_impulse).max(0.);
-            contact.set_accumulated_normal_impulse(to_apply);
+            let previous = contact.accumulated_normal_impulse();
+            let accumulated = (previous + normal_impulse).max(0.);
+            contact.set_accumulated_normal_impulse(accumulated);
 
-            let to_apply = (contact.accumulated_normal_impulse() - to_apply) * contact.normal();
+            let to_apply = (accumulated - previous) * contact.normal();
 
             // Applying the normal impulse
+            incident_body.apply_impulse_at(to_apply, contact.to_incident());
+            reference_body.apply_impulse_at(-to_apply, contact.to_reference());
+        }
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        for contact in self.manifold.iter() {
+            contact.draw(shapes);
+        }
+    }
+
+    fn pre_solve_substep(&mut self, inv_h: f32, first: bool) {
+        if !self.enabled {
+            self.reset_impulses();
+            return;
+        }
+
+        {
+            let incident_body = self.incident_body.read().expect("Body lock was poisoned.");
+            let reference_body = self.reference_body.read().expect("Body lock was poisoned.");
+
+            // Masses and anchors stay as they were at the start of the step
+            let sub_step = match self.sub_step {
+                Some(sub_step) if !first => SubStep {
+                    inv_h,
+                    use_bias: true,
+                    ..sub_step
+                },
+                _ => {
+                    self.fixed_velocities = (
+                        incident_body.fixed_velocity(),
+                        reference_body.fixed_velocity(),
+                    );
+                    (self.mixed_friction, self.mixed_restitution) = Self::mix_materials(
+                        self.friction,
+                        self.restitution,
+                        &incident_body,
+                        &reference_body,
+                    );
+
+                    for contact in self.manifold.iter_mut() {
+                        if contact.is_enabled() {
+                            Self::prepare_contact(contact, &incident_body, &reference_body);
+                        } else {
+                            contact.set_accumulated_normal_impulse(0.);
+                            contact.set_accumulated_tangent_impulse(0.);
+                        }
+                    }
+
+                    SubStep {
+                        softness: Softness::new(
+                            CONTACT_FREQUENCY.min(0.25 * inv_h),
+                            CONTACT_DAMPING_RATIO,
+                            inv_h,
+                        ),
+                        inv_h,
+                        use_bias: true,
+                        start_rotations: (incident_body.rotation, reference_body.rotation),
+                    }
+                }
+            };
+            self.sub_step = Some(sub_step);
+
+            // How far the anchors, which started out on the same point, moved apart along the
+            // normal
+            let (incident_turn, reference_turn) = (
+                incident_body.rotation - sub_step.start_rotations.0,
+                reference_body.rotation - sub_step.start_rotations.1,
+            );
+            for contact in self.manifold.iter_mut() {
+                let incident_anchor = incident_body.center_of_gravity()
+                    + rotate(contact.to_incident(), incident_turn);
+                let reference_anchor = reference_body.center_of_gravity()
+                    + rotate(contact.to_reference(), reference_turn);
+
+                contact.set_separation(
+                    contact.penetration()
+                        + (incident_anchor - reference_anchor).dot(&contact.normal()),
+                );
+            }
+        }
 
-            incident_body.apply_impulse(to_apply);
-            incident_body.apply_angular_impulse(cross(contact.to_incident(), to_apply));
+        self.warm_start();
+    }
 
-            reference_body.apply_impulse(-to_apply);
-            reference_body.apply_angular_impulse(cross(contact.to_reference(), -to_apply));
+    fn relax(&mut self) {
+        if let Some(sub_step) = self.sub_step.as_mut() {
+            sub_step.use_bias = false;
         }
+
+        self.solve();
     }
 
-    fn draw(&self, handle: &mut raylib::prelude::RaylibMode2D<raylib::prelude::RaylibDrawHandle>) {
-        for contact in self.manifold.iter() {
-            contact.draw(handle);
+    fn apply_restitution(&mut self) {
+        if !self.enabled || self.mixed_restitution == 0. {
+            return;
+        }
+
+        let (mut incident_body, mut reference_body) = SolverBody::lock_pair(
+            &self.incident_body,
+            &self.reference_body,
+            self.fixed_velocities,
+        );
+
+        for contact in self.manifold.iter_mut() {
+            // Only contacts that came in fast and actually pushed bounce
+            if !contact.is_enabled()
+                || contact.normal_speed() > -RESTITUTION_THRESHOLD
+                || contact.accumulated_normal_impulse() == 0.
+            {
+                continue;
+            }
+
+            let normal_speed = (incident_body.velocity_at(contact.to_incident())
+                - reference_body.velocity_at(contact.to_reference()))
+            .dot(&contact.normal());
+            let impulse = contact.effective_mass()
+                * (-normal_speed - self.mixed_restitution * contact.normal_speed());
+
+            let previous = contact.accumulated_normal_impulse();
+            let accumulated = (previous + impulse).max(0.);
+            contact.set_accumulated_normal_impulse(accumulated);
+
+            let to_apply = (accumulated - previous) * contact.normal();
+            incident_body.apply_impulse_at(to_apply, contact.to_incident());
+            reference_body.apply_impulse_at(-to_apply, contact.to_reference());
         }
     }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        // Pushing the incident body out of the reference body
+        self.manifold
+            .iter()
+            .map(|contact| {
+                let tangent = Vec2::new(-contact.normal().y, contact.normal().x);
+
+                contact.accumulated_normal_impulse() * contact.normal()
+                    + contact.accumulated_tangent_impulse() * tangent
+            })
+            .sum::<Vec2>()
+            * inv_dt
+    }
+
+    fn reaction_torque(&self, _inv_dt: f32) -> f32 {
+        0.
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.incident_body, &self.reference_body]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 impl CollisionConstraint {
     pub fn new(
         manifold: Vec<ContactPoint>,
-        incident_body: Rc<RefCell<Body>>,
-        reference_body: Rc<RefCell<Body>>,
+        incident_body: SharedBody,
+        reference_body: SharedBody,
     ) -> Self {
         Self {
             manifold,
             incident_body,
             reference_body,
+            fixed_velocities: (None, None),
+            enabled: true,
+            friction: None,
+            restitution: None,
+            tangent_speed: 0.,
+            mixed_friction: 0.,
+            mixed_restitution: 0.,
+            sub_step: None,
+        }
+    }
+
+    fn reset_impulses(&mut self) {
+        for contact in self.manifold.iter_mut() {
+            contact.set_accumulated_normal_impulse(0.);
+            contact.set_accumulated_tangent_impulse(0.);
+        }
+    }
+
+    /// Applies the impulses carried over from the last step or substep.
+    fn warm_start(&self) {
+        let (mut incident_body, mut reference_body) = SolverBody::lock_pair(
+            &self.incident_body,
+            &self.reference_body,
+            self.fixed_velocities,
+        );
+
+        for contact in self.manifold.iter().filter(|contact| contact.is_enabled()) {
+            let impulse = contact.accumulated_impulse();
+            incident_body.apply_impulse_at(impulse, contact.to_incident());
+            reference_body.apply_impulse_at(-impulse, contact.to_reference());
+        }
+    }
+
+    /// Friction and restitution of the pair, unless a pre-solve hook overrode them. Mixed like
+    /// Box2D, a slippery surface makes the pair slippery and a bouncy one bounces.
+    fn mix_materials(
+        friction: Option<f32>,
+        restitution: Option<f32>,
+        incident_body: &Body,
+        reference_body: &Body,
+    ) -> (f32, f32) {
+        (
+            friction
+                .unwrap_or_else(|| (incident_body.friction() * reference_body.friction()).sqrt()),
+            restitution.unwrap_or_else(|| {
+                incident_body
+                    .restitution()
+                    .max(reference_body.restitution())
+            }),
+        )
+    }
+
+    /// Anchors, effective masses and the speed the bodies come together at.
+    fn prepare_contact(contact: &mut ContactPoint, incident_body: &Body, reference_body: &Body) {
+        contact.set_to_incident(contact.point() - incident_body.center_of_gravity());
+        contact.set_to_reference(contact.point() - reference_body.center_of_gravity());
+
+        let net_inv_mass = incident_body.inv_mass() + reference_body.inv_mass();
+
+        // Effective mass (mass that affects the linear push done to the contact point)
+        let incident_normal_mass = contact.to_incident().dot(&contact.normal());
+        let reference_normal_mass = contact.to_reference().dot(&contact.normal());
+
+        let net_normal_mass = net_inv_mass
+            + incident_body.inv_inertia()
+                * (contact.to_incident().norm_squared()
+                    - (incident_normal_mass * incident_normal_mass))
+            + reference_body.inv_inertia()
+                * (contact.to_reference().norm_squared()
+                    - (reference_normal_mass * reference_normal_mass));
+
+        contact.set_effective_mass(1. / net_normal_mass);
+
+        // Tangent mass
+        let tangent = Vec2::new(-contact.normal().y, contact.normal().x);
+        let incident_tangent_mass = contact.to_incident().dot(&tangent);
+        let reference_tangent_mass = contact.to_reference().dot(&tangent);
+
+        let net_tangent_mass = net_inv_mass
+            + incident_body.inv_inertia()
+                * (contact.to_incident().norm_squared()
+                    - (incident_tangent_mass * incident_tangent_mass))
+            + reference_body.inv_inertia()
+                * (contact.to_reference().norm_squared()
+                    - (reference_tangent_mass * reference_tangent_mass));
+
+        contact.set_tangent_mass(1. / net_tangent_mass);
+
+        let normal_speed = (incident_body.velocity_at(contact.to_incident())
+            - reference_body.velocity_at(contact.to_reference()))
+        .dot(&contact.normal());
+        contact.set_normal_speed(normal_speed);
+    }
+
+    /// Target separating speed of a contact, along with the mass and impulse scales of the soft
+    /// constraint it is solved with.
+    fn normal_bias(sub_step: Option<SubStep>, contact: &ContactPoint) -> (f32, f32, f32) {
+        let Some(sub_step) = sub_step else {
+            return (contact.bias(), 1., 0.);
+        };
+
+        let separation = contact.separation() + ALLOWED_PENETRATION;
+        if separation > 0. {
+            // Not touching anymore, the bodies may still close the gap within the substep
+            (-separation * sub_step.inv_h, 1., 0.)
+        } else if sub_step.use_bias {
+            (
+                (-sub_step.softness.bias_rate * separation).min(CONTACT_PUSH_SPEED),
+                sub_step.softness.mass_scale,
+                sub_step.softness.impulse_scale,
+            )
+        } else {
+            (0., 1., 0.)
         }
     }
 
+    pub fn manifold(&self) -> &[ContactPoint] {
+        &self.manifold
+    }
+
+    pub fn manifold_mut(&mut self) -> &mut [ContactPoint] {
+        &mut self.manifold
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn set_friction(&mut self, friction: f32) {
+        self.friction = Some(friction);
+    }
+
+    pub fn set_restitution(&mut self, restitution: f32) {
+        self.restitution = Some(restitution);
+    }
+
+    /// Target speed of the incident body over the reference body along the contact tangent.
+    pub fn set_tangent_speed(&mut self, tangent_speed: f32) {
+        self.tangent_speed = tangent_speed;
+    }
+
+    pub fn has_incident(&self, body: &SharedBody) -> bool {
+        Arc::ptr_eq(&self.incident_body, body)
+    }
+
+    /// Replaces the manifold with the one of this step, carrying impulses over to the points that
+    /// are still there. Whatever a pre-solve hook changed last step is undone.
     pub fn update_manifold(&mut self, manifold: Vec<ContactPoint>) {
+        self.enabled = true;
+        self.friction = None;
+        self.restitution = None;
+        self.tangent_speed = 0.;
+
         self.manifold = manifold
             .into_iter()
             .map(|mut new_contact| {
@@ -138,22 +496,15 @@ impl CollisionConstraint {
             .collect();
     }
 
+    /// Both polygons are expected to already be in world space.
     pub fn generate_manifold(
         normal: Vec2,
-        incident_body: &Body,
-        reference_body: &Body,
+        incident_polygon: &Polygon,
+        reference_polygon: &Polygon,
     ) -> Vec<ContactPoint> {
-        let incident_polygon = (*incident_body)
-            .collider_in_world()
-            .expect("generate_manifold was somehow called for a body without a polygon.");
-
         let (incident_face, incident_index) =
             incident_polygon.get_significant_face_with_index(-normal);
 
-        let reference_polygon = (*reference_body)
-            .collider_in_world()
-            .expect("generate_manifold was somehow called for a body without a polygon.");
-
         let (reference_face, reference_index) =
             reference_polygon.get_significant_face_with_index(normal);
 
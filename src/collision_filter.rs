@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+
+/// Decides which colliders are tested against each other, like the filters of Box2D.
+///
+/// Two colliders collide if each one's mask contains the other's category. Colliders that share
+/// a nonzero group skip that check: a positive group always collides, a negative group never does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CollisionFilter {
+    #[serde(default = "default_category")]
+    pub category: u32,
+    #[serde(default = "default_mask")]
+    pub mask: u32,
+    #[serde(default)]
+    pub group: i32,
+}
+
+fn default_category() -> u32 {
+    1
+}
+
+fn default_mask() -> u32 {
+    u32::MAX
+}
+
+impl Default for CollisionFilter {
+    fn default() -> Self {
+        Self {
+            category: default_category(),
+            mask: default_mask(),
+            group: 0,
+        }
+    }
+}
+
+impl CollisionFilter {
+    pub fn should_collide(&self, other: &Self) -> bool {
+        if self.group != 0 && self.group == other.group {
+            return self.group > 0;
+        }
+
+        self.mask & other.category != 0 && other.mask & self.category != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(category: u32, mask: u32, group: i32) -> CollisionFilter {
+        CollisionFilter {
+            category,
+            mask,
+            group,
+        }
+    }
+
+    #[test]
+    fn default_filters_collide() {
+        assert!(CollisionFilter::default().should_collide(&CollisionFilter::default()));
+    }
+
+    #[test]
+    fn both_masks_have_to_agree() {
+        let player = filter(0b01, u32::MAX, 0);
+        let ghost = filter(0b10, !0b01, 0);
+
+        assert!(!player.should_collide(&ghost));
+        assert!(!ghost.should_collide(&player));
+        assert!(player.should_collide(&filter(0b10, u32::MAX, 0)));
+    }
+
+    #[test]
+    fn shared_groups_override_the_masks() {
+        let positive = filter(0b01, 0, 3);
+        let negative = filter(0b01, u32::MAX, -3);
+
+        assert!(positive.should_collide(&positive));
+        assert!(!negative.should_collide(&negative));
+    }
+
+    #[test]
+    fn different_groups_fall_back_to_the_masks() {
+        assert!(filter(0b01, u32::MAX, -1).should_collide(&filter(0b01, u32::MAX, -2)));
+        assert!(!filter(0b01, 0, 1).should_collide(&filter(0b01, u32::MAX, 2)));
+    }
+}
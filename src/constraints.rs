@@ -1,9 +1,206 @@
-use raylib::prelude::{RaylibDrawHandle, RaylibMode2D};
+use std::any::Any;
 
-pub trait Constraint {
+use nalgebra_glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    body::{Body, SharedBody, SolverBody},
+    debug_draw::DebugShape,
+    math::cross,
+};
+
+/// How much of the position error joints try to correct each step (Baumgarte stabilization).
+pub const BIAS_FACTOR: f32 = 0.2;
+
+/// Constraints need to be `Send` so that islands can be solved on worker threads.
+pub trait Constraint: Send {
     fn pre_solve(&mut self, dt: f32);
 
     fn solve(&mut self);
 
-    fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>);
+    /// Sub-stepped worlds call this at the start of every substep instead of `pre_solve` once
+    /// per step, `first` is set on the first substep of a step. Preparing again from where the
+    /// bodies moved to is what keeps the bias up to date.
+    fn pre_solve_substep(&mut self, inv_h: f32, _first: bool) {
+        self.pre_solve(inv_h);
+    }
+
+    /// Solves again without the bias once the positions of a substep were integrated, so the
+    /// speed used to correct positions does not carry over. Only soft contacts relax.
+    fn relax(&mut self) {}
+
+    /// Bounces happen once after the last substep, so relaxing cannot take them out.
+    fn apply_restitution(&mut self) {}
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>);
+
+    /// Force the constraint applied during the last step, from the impulses it accumulated.
+    /// Joints report the force on body B.
+    fn reaction_force(&self, inv_dt: f32) -> Vec2;
+
+    /// Torque the constraint applied during the last step, on body B for joints.
+    fn reaction_torque(&self, inv_dt: f32) -> f32;
+
+    /// The bodies this constraint acts on. Used to group constraints into islands.
+    fn bodies(&self) -> Vec<&SharedBody>;
+
+    /// Lets the world hand out the concrete joint type, e.g. to change a motor at runtime.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Range a joint coordinate (angle or translation) is kept within.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Limits {
+    pub lower: f32,
+    pub upper: f32,
+}
+
+/// Drives a joint coordinate at a target speed. `max_force` is a torque for angular motors.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Motor {
+    pub speed: f32,
+    pub max_force: f32,
+}
+
+/// Reaction force and torque past which a joint breaks and is removed from the world.
+#[derive(Debug, Clone, Copy)]
+pub struct BreakLimits {
+    pub force: f32,
+    pub torque: f32,
+}
+
+/// A direction a joint constrains the relative motion of its bodies along, like the axis of a
+/// prismatic or wheel joint and the perpendicular to it. The crosses are the lever arms of the
+/// bodies, so an impulse along the direction turns them as well.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Axis {
+    pub direction: Vec2,
+    pub cross_a: f32,
+    pub cross_b: f32,
+}
+
+impl Axis {
+    /// `to_anchor_a` and `to_anchor_b` go from the centers of gravity to the anchors, `delta`
+    /// from anchor A to anchor B.
+    pub fn new(direction: Vec2, to_anchor_a: Vec2, to_anchor_b: Vec2, delta: Vec2) -> Self {
+        Self {
+            direction,
+            cross_a: cross(delta + to_anchor_a, direction),
+            cross_b: cross(to_anchor_b, direction),
+        }
+    }
+
+    /// How fast anchor B moves away from anchor A along the direction.
+    pub fn speed(&self, body_a: &SolverBody, body_b: &SolverBody) -> f32 {
+        self.direction.dot(&(body_b.velocity() - body_a.velocity()))
+            + self.cross_b * body_b.angular_velocity()
+            - self.cross_a * body_a.angular_velocity()
+    }
+
+    pub fn effective_mass(&self, body_a: &Body, body_b: &Body) -> f32 {
+        effective_mass(
+            body_a.inv_mass()
+                + body_b.inv_mass()
+                + body_a.inv_inertia() * self.cross_a * self.cross_a
+                + body_b.inv_inertia() * self.cross_b * self.cross_b,
+        )
+    }
+
+    /// Pushes body B along the direction and body A the opposite way.
+    pub fn apply(&self, body_a: &mut SolverBody, body_b: &mut SolverBody, impulse: f32) {
+        body_a.apply_impulse(-impulse * self.direction);
+        body_a.apply_angular_impulse(-impulse * self.cross_a);
+
+        body_b.apply_impulse(impulse * self.direction);
+        body_b.apply_angular_impulse(impulse * self.cross_b);
+    }
+}
+
+/// Inverse of an inverse mass, zero when none of the bodies can move.
+pub fn effective_mass(inv_mass: f32) -> f32 {
+    if inv_mass > 0. {
+        1. / inv_mass
+    } else {
+        0.
+    }
+}
+
+/// Coefficients of a soft constraint, which behaves like a damped spring towards the solved
+/// position instead of a rigid rod. Solving for an impulse looks like:
+///
+/// `impulse = -mass_scale * effective_mass * (velocity_error + bias_rate * position_error)
+///     - impulse_scale * accumulated_impulse`
+///
+/// Based on the soft step formulation of Box2D v3.
+#[derive(Debug, Clone, Copy)]
+pub struct Softness {
+    pub bias_rate: f32,
+    pub mass_scale: f32,
+    pub impulse_scale: f32,
+}
+
+impl Softness {
+    /// `frequency` is in hertz and `damping_ratio` is 1 for critical damping. A frequency of zero
+    /// gives a rigid constraint.
+    pub fn new(frequency: f32, damping_ratio: f32, inv_dt: f32) -> Self {
+        if frequency <= 0. {
+            return Self::rigid(inv_dt);
+        }
+
+        let dt = 1. / inv_dt;
+        let omega = 2. * std::f32::consts::PI * frequency;
+        let a1 = 2. * damping_ratio + dt * omega;
+        let a2 = dt * omega * a1;
+        let a3 = 1. / (1. + a2);
+
+        Self {
+            bias_rate: omega / a1,
+            mass_scale: a2 * a3,
+            impulse_scale: a3,
+        }
+    }
+
+    /// Plain Baumgarte stabilization, like the rest of the joints.
+    pub fn rigid(inv_dt: f32) -> Self {
+        Self {
+            bias_rate: BIAS_FACTOR * inv_dt,
+            mass_scale: 1.,
+            impulse_scale: 0.,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INV_DT: f32 = 60.;
+
+    #[test]
+    fn zero_frequency_is_rigid() {
+        let softness = Softness::new(0., 0.7, INV_DT);
+
+        assert_eq!(softness.bias_rate, BIAS_FACTOR * INV_DT);
+        assert_eq!(softness.mass_scale, 1.);
+        assert_eq!(softness.impulse_scale, 0.);
+    }
+
+    #[test]
+    fn scales_add_up_to_one() {
+        for frequency in [0.5, 5., 50.] {
+            let softness = Softness::new(frequency, 0.7, INV_DT);
+
+            assert!((softness.mass_scale + softness.impulse_scale - 1.).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn stiffer_constraints_push_harder() {
+        let soft = Softness::new(1., 1., INV_DT);
+        let stiff = Softness::new(10., 1., INV_DT);
+
+        assert!(stiff.mass_scale > soft.mass_scale);
+        assert!(stiff.bias_rate > soft.bias_rate);
+        assert!(stiff.mass_scale < 1.);
+    }
 }
@@ -1,8 +1,7 @@
 use catppuccin::ColorName;
 use nalgebra_glm::Vec2;
-use raylib::prelude::*;
 
-use crate::{body::Body, color, plane::Plane};
+use crate::{color, debug_draw::DebugShape, plane::Plane};
 
 /// This is the way that each contact point will identify itself with.
 #[derive(Clone)]
@@ -61,6 +60,12 @@ pub struct ContactPoint {
     tangent_mass: f32,
 
     bias: f32,
+    /// Relative speed along the normal when the step started, negative when approaching
+    normal_speed: f32,
+    /// Penetration kept up to date over the substeps of a sub-stepped world
+    separation: f32,
+    /// Disabled contacts are skipped by the solver for this step
+    enabled: bool,
 
     id: ContactID,
 
@@ -98,61 +103,49 @@ impl ContactPoint {
             effective_mass: f32::default(),
             tangent_mass: f32::default(),
             bias: 0.,
+            normal_speed: 0.,
+            separation: penetration,
+            enabled: true,
 
             incident_plane,
             reference_plane,
         }
     }
 
-    pub fn apply_accumulated_impulses(
-        &mut self,
-        incident_body: &mut Body,
-        reference_body: &mut Body,
-    ) {
+    /// Normal and friction impulse accumulated so far, as applied to the incident body.
+    pub fn accumulated_impulse(&self) -> Vec2 {
         let tangent = Vec2::new(-self.normal().y, self.normal().x);
-        let impulse = self.accumulated_normal_impulse() * self.normal()
-            + self.accumulated_tangent_impulse() * tangent;
-
-        let cross = |a: Vec2, b: Vec2| -> f32 { a.x * b.y - a.y * b.x };
 
-        incident_body.apply_impulse(impulse);
-        incident_body.apply_angular_impulse(cross(self.to_incident(), impulse));
-
-        reference_body.apply_impulse(-impulse);
-        reference_body.apply_angular_impulse(cross(self.to_reference(), -impulse));
+        self.accumulated_normal_impulse() * self.normal()
+            + self.accumulated_tangent_impulse() * tangent
     }
 
-    pub fn draw(
-        &self,
-        handle: &mut raylib::prelude::RaylibMode2D<raylib::prelude::RaylibDrawHandle>,
-    ) {
+    pub fn draw(&self, shapes: &mut Vec<DebugShape>) {
         self.incident_plane
-            .draw(handle, &color::get(ColorName::Blue));
+            .draw(shapes, &color::get(ColorName::Blue));
         self.reference_plane
-            .draw(handle, &color::get(ColorName::Yellow));
-
-        handle.draw_circle(
-            self.point.x as i32,
-            self.point.y as i32,
-            10.,
-            color::get(ColorName::Green),
-        );
-
-        let end_pos = self.point + self.normal * -self.penetration;
-        let end_pos = Vector2 {
-            x: end_pos.x,
-            y: end_pos.y,
-        };
-
-        handle.draw_line_ex(
-            Vector2 {
-                x: self.point.x,
-                y: self.point.y,
-            },
-            end_pos,
-            3.,
-            color::get(ColorName::Peach),
-        );
+            .draw(shapes, &color::get(ColorName::Yellow));
+
+        shapes.push(DebugShape::Circle {
+            center: self.point,
+            radius: 10.,
+            color: color::get(ColorName::Green),
+        });
+
+        shapes.push(DebugShape::Line {
+            start: self.point,
+            end: self.point + self.normal * -self.penetration,
+            thickness: 3.,
+            color: color::get(ColorName::Peach),
+        });
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
     }
 
     pub fn id(&self) -> &ContactID {
@@ -237,4 +230,20 @@ impl ContactPoint {
     pub fn set_bias(&mut self, bias: f32) {
         self.bias = bias;
     }
+
+    pub fn normal_speed(&self) -> f32 {
+        self.normal_speed
+    }
+
+    pub fn set_normal_speed(&mut self, normal_speed: f32) {
+        self.normal_speed = normal_speed;
+    }
+
+    pub fn separation(&self) -> f32 {
+        self.separation
+    }
+
+    pub fn set_separation(&mut self, separation: f32) {
+        self.separation = separation;
+    }
 }
@@ -0,0 +1,72 @@
+use nalgebra_glm::Vec2;
+use raylib::prelude::*;
+
+/// Lines and circles that joints, contacts and force generators show themselves with. They are
+/// recorded rather than drawn right away, so a world stepped on another thread looks the same as
+/// one stepped in between frames.
+#[derive(Debug, Clone)]
+pub enum DebugShape {
+    Line {
+        start: Vec2,
+        end: Vec2,
+        thickness: f32,
+        color: Color,
+    },
+    /// Connects every point to the next one
+    LineStrip { points: Vec<Vec2>, color: Color },
+    Circle {
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    },
+    CircleLines {
+        center: Vec2,
+        radius: f32,
+        color: Color,
+    },
+    SquareLines {
+        center: Vec2,
+        size: f32,
+        color: Color,
+    },
+}
+
+impl DebugShape {
+    pub fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>) {
+        let vector = |point: &Vec2| Vector2::new(point.x, point.y);
+
+        match self {
+            DebugShape::Line {
+                start,
+                end,
+                thickness,
+                color,
+            } => handle.draw_line_ex(vector(start), vector(end), *thickness, *color),
+            DebugShape::LineStrip { points, color } => {
+                let points: Vec<Vector2> = points.iter().map(vector).collect();
+                handle.draw_line_strip(&points, *color);
+            }
+            DebugShape::Circle {
+                center,
+                radius,
+                color,
+            } => handle.draw_circle_v(vector(center), *radius, *color),
+            DebugShape::CircleLines {
+                center,
+                radius,
+                color,
+            } => handle.draw_circle_lines(center.x as i32, center.y as i32, *radius, *color),
+            DebugShape::SquareLines {
+                center,
+                size,
+                color,
+            } => handle.draw_rectangle_lines(
+                (center.x - size / 2.) as i32,
+                (center.y - size / 2.) as i32,
+                *size as i32,
+                *size as i32,
+                *color,
+            ),
+        }
+    }
+}
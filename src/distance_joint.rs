@@ -0,0 +1,250 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    math::cross,
+};
+
+/// Keeps the anchor points of two bodies at a distance from each other. If the minimum and
+/// maximum lengths are the same the joint is rigid, otherwise the anchors are free to move
+/// within that range.
+pub struct DistanceJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+
+    min_length: f32,
+    max_length: f32,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    to_anchor_a: Vec2,
+    to_anchor_b: Vec2,
+    axis: Vec2,
+    current_length: f32,
+    effective_mass: f32,
+
+    // Accumulated over frames for warm starting
+    impulse: f32,
+    lower_impulse: f32,
+    upper_impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl DistanceJoint {
+    /// The anchors are in the same local space as the collider points of their bodies.
+    pub fn new(
+        body_a: SharedBody,
+        local_anchor_a: Vec2,
+        body_b: SharedBody,
+        local_anchor_b: Vec2,
+        min_length: f32,
+        max_length: f32,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            min_length: min_length.min(max_length),
+            max_length: max_length.max(min_length),
+            fixed_velocities: (None, None),
+            to_anchor_a: Vec2::default(),
+            to_anchor_b: Vec2::default(),
+            axis: Vec2::default(),
+            current_length: 0.,
+            effective_mass: 0.,
+            impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// A rope only keeps the anchors from getting further apart than `max_length`.
+    pub fn rope(
+        body_a: SharedBody,
+        local_anchor_a: Vec2,
+        body_b: SharedBody,
+        local_anchor_b: Vec2,
+        max_length: f32,
+    ) -> Self {
+        Self::new(
+            body_a,
+            local_anchor_a,
+            body_b,
+            local_anchor_b,
+            0.,
+            max_length,
+        )
+    }
+
+    pub fn is_rigid(&self) -> bool {
+        self.max_length - self.min_length <= f32::EPSILON
+    }
+
+    /// Bias for a one sided limit. When the limit is not reached yet the bodies are allowed to
+    /// close the gap within this step, otherwise the error is corrected like every other joint.
+    fn limit_bias(&self, error: f32) -> f32 {
+        if error > 0. {
+            error * self.inv_dt
+        } else {
+            BIAS_FACTOR * error * self.inv_dt
+        }
+    }
+}
+
+impl Constraint for DistanceJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        self.to_anchor_a = anchor_a - body_a.center_of_gravity();
+        self.to_anchor_b = anchor_b - body_b.center_of_gravity();
+
+        let delta = anchor_b - anchor_a;
+        self.current_length = delta.magnitude();
+        self.axis = if self.current_length > f32::EPSILON {
+            delta / self.current_length
+        } else {
+            Vec2::default()
+        };
+
+        let cross_a = cross(self.to_anchor_a, self.axis);
+        let cross_b = cross(self.to_anchor_b, self.axis);
+        let inv_mass = body_a.inv_mass()
+            + body_a.inv_inertia() * cross_a * cross_a
+            + body_b.inv_mass()
+            + body_b.inv_inertia() * cross_b * cross_b;
+
+        self.effective_mass = if inv_mass > 0. { 1. / inv_mass } else { 0. };
+
+        if self.is_rigid() {
+            self.lower_impulse = 0.;
+            self.upper_impulse = 0.;
+        } else {
+            self.impulse = 0.;
+        }
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        let impulse = (self.impulse + self.lower_impulse - self.upper_impulse) * self.axis;
+        body_a.apply_impulse_at(-impulse, self.to_anchor_a);
+        body_b.apply_impulse_at(impulse, self.to_anchor_b);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        if self.is_rigid() {
+            let relative_velocity =
+                body_b.velocity_at(self.to_anchor_b) - body_a.velocity_at(self.to_anchor_a);
+            let error = self.current_length - self.min_length;
+            let bias = BIAS_FACTOR * self.inv_dt * error;
+
+            let impulse = -self.effective_mass * (relative_velocity.dot(&self.axis) + bias);
+            self.impulse += impulse;
+
+            let impulse = impulse * self.axis;
+            body_a.apply_impulse_at(-impulse, self.to_anchor_a);
+            body_b.apply_impulse_at(impulse, self.to_anchor_b);
+            return;
+        }
+
+        // Lower limit, the anchors can only be pushed apart
+        {
+            let relative_velocity =
+                body_b.velocity_at(self.to_anchor_b) - body_a.velocity_at(self.to_anchor_a);
+            let bias = self.limit_bias(self.current_length - self.min_length);
+
+            let impulse = -self.effective_mass * (relative_velocity.dot(&self.axis) + bias);
+            let accumulated = (self.lower_impulse + impulse).max(0.);
+            let impulse = (accumulated - self.lower_impulse) * self.axis;
+            self.lower_impulse = accumulated;
+
+            body_a.apply_impulse_at(-impulse, self.to_anchor_a);
+            body_b.apply_impulse_at(impulse, self.to_anchor_b);
+        }
+
+        // Upper limit, the anchors can only be pulled together
+        {
+            let relative_velocity =
+                body_a.velocity_at(self.to_anchor_a) - body_b.velocity_at(self.to_anchor_b);
+            let bias = self.limit_bias(self.max_length - self.current_length);
+
+            let impulse = -self.effective_mass * (relative_velocity.dot(&self.axis) + bias);
+            let accumulated = (self.upper_impulse + impulse).max(0.);
+            let impulse = (accumulated - self.upper_impulse) * self.axis;
+            self.upper_impulse = accumulated;
+
+            body_a.apply_impulse_at(impulse, self.to_anchor_a);
+            body_b.apply_impulse_at(-impulse, self.to_anchor_b);
+        }
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor_a = self
+            .body_a
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_a);
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let color = color::get(ColorName::Lavender);
+
+        shapes.push(DebugShape::Line {
+            start: anchor_a,
+            end: anchor_b,
+            thickness: 2.,
+            color,
+        });
+        for center in [anchor_a, anchor_b] {
+            shapes.push(DebugShape::Circle {
+                center,
+                radius: 4.,
+                color,
+            });
+        }
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        (self.impulse + self.lower_impulse - self.upper_impulse) * inv_dt * self.axis
+    }
+
+    fn reaction_torque(&self, _inv_dt: f32) -> f32 {
+        0.
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
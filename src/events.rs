@@ -0,0 +1,90 @@
+use std::fmt;
+
+use nalgebra_glm::Vec2;
+
+use crate::{arena::BodyHandle, world::JointHandle};
+
+/// A contact point of a manifold.
+#[derive(Debug, Clone, Copy)]
+pub struct ManifoldPoint {
+    pub point: Vec2,
+    /// Negative while the bodies overlap
+    pub penetration: f32,
+}
+
+/// State of a touching pair of bodies after the solver ran. The normal points from body A to
+/// body B, and the impulses are the totals over every point of the manifold.
+#[derive(Debug, Clone)]
+pub struct Contact {
+    pub body_a: BodyHandle,
+    pub body_b: BodyHandle,
+    pub points: Vec<ManifoldPoint>,
+    pub normal: Vec2,
+    pub normal_impulse: f32,
+    pub tangent_impulse: f32,
+}
+
+/// Something that happened during a step of the world.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// The joint went past its break limits and was removed from the world
+    JointBroken {
+        joint: JointHandle,
+        force: Vec2,
+        torque: f32,
+    },
+    /// A body started overlapping a sensor
+    SensorBegin {
+        sensor: BodyHandle,
+        visitor: BodyHandle,
+    },
+    /// A body stopped overlapping a sensor
+    SensorEnd {
+        sensor: BodyHandle,
+        visitor: BodyHandle,
+    },
+    /// Two bodies started touching
+    ContactBegin(Contact),
+    /// Two bodies kept touching since the last step
+    ContactPersist(Contact),
+    /// Two bodies stopped touching
+    ContactEnd {
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+    },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::JointBroken {
+                joint,
+                force,
+                torque,
+            } => write!(
+                f,
+                "{joint:?} broke (force {:.0}, torque {torque:.0})",
+                force.magnitude()
+            ),
+            Event::SensorBegin { sensor, visitor } => {
+                write!(f, "{visitor:?} entered sensor {sensor:?}")
+            }
+            Event::SensorEnd { sensor, visitor } => {
+                write!(f, "{visitor:?} left sensor {sensor:?}")
+            }
+            Event::ContactBegin(contact) => write!(
+                f,
+                "{:?} hit {:?} (impulse {:.0})",
+                contact.body_a, contact.body_b, contact.normal_impulse
+            ),
+            Event::ContactPersist(contact) => write!(
+                f,
+                "{:?} is touching {:?} (impulse {:.0})",
+                contact.body_a, contact.body_b, contact.normal_impulse
+            ),
+            Event::ContactEnd { body_a, body_b } => {
+                write!(f, "{body_a:?} stopped touching {body_b:?}")
+            }
+        }
+    }
+}
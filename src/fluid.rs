@@ -0,0 +1,97 @@
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    arena::BodyHandle, body::SharedBody, color, debug_draw::DebugShape,
+    force_generator::ForceGenerator, plane::Plane,
+};
+
+/// A rectangle of water (or any other fluid) hanging `depth` below its surface. Bodies in it are
+/// pushed against the gravity of the world by the weight of the fluid they displace, at the
+/// centroid of their submerged part, and slowed down by drag.
+pub struct FluidVolume {
+    /// The surface first, then the far side, the bottom and the near side. Every normal points
+    /// out of the fluid.
+    bounds: [Plane; 4],
+    density: f32,
+    linear_drag: f32,
+    angular_drag: f32,
+}
+
+impl FluidVolume {
+    /// The fluid lies on the side the surface normal points away from, which is below the
+    /// surface when `surface_start` is left of `surface_end`.
+    pub fn new(
+        surface_start: Vec2,
+        surface_end: Vec2,
+        depth: f32,
+        density: f32,
+        linear_drag: f32,
+        angular_drag: f32,
+    ) -> Self {
+        let down = -Plane::new(surface_start, surface_end)
+            .get_normal()
+            .normalize()
+            * depth;
+        let (bottom_start, bottom_end) = (surface_start + down, surface_end + down);
+
+        Self {
+            bounds: [
+                Plane::new(surface_start, surface_end),
+                Plane::new(surface_end, bottom_end),
+                Plane::new(bottom_end, bottom_start),
+                Plane::new(bottom_start, surface_start),
+            ],
+            density,
+            linear_drag,
+            angular_drag,
+        }
+    }
+}
+
+impl ForceGenerator for FluidVolume {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, gravity: Vec2) {
+        for (_, body) in bodies {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            if !body.is_dynamic() || body.is_sensor() {
+                continue;
+            }
+
+            let Some(submerged) = body.collider_in_world().and_then(|collider| {
+                self.bounds
+                    .iter()
+                    .try_fold(collider, |polygon, plane| polygon.clip(plane))
+            }) else {
+                continue;
+            };
+
+            let (area, centroid) = submerged.area_and_centroid();
+            if area <= 0. {
+                continue;
+            }
+
+            // The displaced fluid is pulled down by the gravity of the world, the body is pushed the
+            // other way
+            let buoyancy = -self.density * area * gravity;
+            let velocity = body.velocity_at(centroid - body.center_of_gravity());
+            let drag = -self.linear_drag * area * velocity;
+            body.apply_force_at_point(buoyancy + drag, centroid);
+
+            let angular_drag = -self.angular_drag * area * body.angular_velocity();
+            body.apply_torque(angular_drag);
+        }
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let color = color::get(ColorName::Sapphire);
+
+        for plane in self.bounds.iter() {
+            shapes.push(DebugShape::Line {
+                start: plane.start(),
+                end: plane.end(),
+                thickness: 2.,
+                color,
+            });
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use nalgebra_glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{arena::BodyHandle, body::SharedBody, debug_draw::DebugShape};
+
+/// Something that keeps pushing on bodies, applied at the start of every step until it is
+/// removed from the world or finishes on its own.
+pub trait ForceGenerator: Send {
+    /// Adds forces or impulses to the bodies for the coming step. The bodies come in ascending
+    /// handle order, `gravity` is the uniform gravity of the world.
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], dt: f32, gravity: Vec2);
+
+    /// Finished generators are removed from the world after they were applied.
+    fn is_finished(&self) -> bool {
+        false
+    }
+
+    /// Energy stored in the field of the generator, for generators whose force has a potential.
+    fn potential_energy(&self, _bodies: &[(BodyHandle, &SharedBody)]) -> f32 {
+        0.
+    }
+
+    /// Most generators have nothing to show.
+    fn draw(&self, _shapes: &mut Vec<DebugShape>) {}
+}
+
+/// Air moving at the same velocity everywhere. Bodies are pushed in proportion to how much
+/// slower than the wind they are, so they end up drifting along with it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Wind {
+    pub velocity: Vec2,
+    /// Force per unit of speed difference
+    pub strength: f32,
+}
+
+impl ForceGenerator for Wind {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        for (_, body) in bodies {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            let force = self.strength * (self.velocity - body.velocity());
+            body.apply_force(force);
+        }
+    }
+}
+
+/// Drag that grows with the square of the speed, so fast bodies slow down a lot more than slow
+/// ones.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AirDrag {
+    #[serde(default)]
+    pub linear: f32,
+    #[serde(default)]
+    pub angular: f32,
+}
+
+impl ForceGenerator for AirDrag {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        for (_, body) in bodies {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            let velocity = body.velocity();
+            let angular_velocity = body.angular_velocity();
+
+            body.apply_force(-self.linear * velocity.magnitude() * velocity);
+            body.apply_torque(-self.angular * angular_velocity.abs() * angular_velocity);
+        }
+    }
+}
+
+/// Pulls a body towards a fixed point in proportion to how far away it is, like a spring with no
+/// rest length. Its center of gravity oscillates around the anchor.
+#[derive(Debug, Clone, Copy)]
+pub struct AnchorSpring {
+    body: BodyHandle,
+    anchor: Vec2,
+    stiffness: f32,
+}
+
+impl AnchorSpring {
+    pub fn new(body: BodyHandle, anchor: Vec2, stiffness: f32) -> Self {
+        Self {
+            body,
+            anchor,
+            stiffness,
+        }
+    }
+
+    fn find<'a>(&self, bodies: &[(BodyHandle, &'a SharedBody)]) -> Option<&'a SharedBody> {
+        bodies
+            .iter()
+            .find(|(handle, _)| *handle == self.body)
+            .map(|(_, body)| *body)
+    }
+}
+
+impl ForceGenerator for AnchorSpring {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        let Some(body) = self.find(bodies) else {
+            return;
+        };
+
+        let mut body = body.write().expect("Body lock was poisoned.");
+        let force = self.stiffness * (self.anchor - body.center_of_gravity());
+        body.apply_force(force);
+    }
+
+    fn potential_energy(&self, bodies: &[(BodyHandle, &SharedBody)]) -> f32 {
+        self.find(bodies).map_or(0., |body| {
+            let body = body.read().expect("Body lock was poisoned.");
+            0.5 * self.stiffness * (self.anchor - body.center_of_gravity()).magnitude_squared()
+        })
+    }
+}
+
+/// Pushes every body within `radius` away from `center` once. The impulse is strongest at the
+/// center and falls off linearly to nothing at the edge.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Explosion {
+    pub center: Vec2,
+    pub radius: f32,
+    /// Impulse a body right at the center would get
+    pub impulse: f32,
+    #[serde(skip)]
+    detonated: bool,
+}
+
+impl Explosion {
+    pub fn new(center: Vec2, radius: f32, impulse: f32) -> Self {
+        Self {
+            center,
+            radius,
+            impulse,
+            detonated: false,
+        }
+    }
+}
+
+impl ForceGenerator for Explosion {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        self.detonated = true;
+
+        for (_, body) in bodies {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            let offset = body.center_of_gravity() - self.center;
+            let distance = offset.magnitude();
+
+            // A body right on top of the explosion has no direction to be pushed in
+            if distance >= self.radius || distance <= f32::EPSILON {
+                continue;
+            }
+
+            let falloff = 1. - distance / self.radius;
+            body.apply_impulse(self.impulse * falloff * offset / distance);
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.detonated
+    }
+}
@@ -0,0 +1,304 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, Body, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    math::{cross, rotate},
+};
+
+/// The coordinate of a revolute or prismatic joint, which is what a gear joint couples. It holds
+/// onto the bodies of the joint rather than the joint itself so it can outlive the borrow.
+pub enum JointCoordinate {
+    /// Relative angle of the bodies
+    Revolute {
+        body_a: SharedBody,
+        body_b: SharedBody,
+        reference_angle: f32,
+    },
+    /// Translation of the anchor of body B along an axis fixed to body A
+    Prismatic {
+        body_a: SharedBody,
+        body_b: SharedBody,
+        local_anchor_a: Vec2,
+        local_anchor_b: Vec2,
+        local_axis: Vec2,
+    },
+}
+
+/// How the coordinate changes with the velocities of the bodies.
+#[derive(Debug, Default, Clone, Copy)]
+struct Jacobian {
+    linear_a: Vec2,
+    angular_a: f32,
+    linear_b: Vec2,
+    angular_b: f32,
+}
+
+impl JointCoordinate {
+    fn bodies(&self) -> (&SharedBody, &SharedBody) {
+        match self {
+            JointCoordinate::Revolute { body_a, body_b, .. }
+            | JointCoordinate::Prismatic { body_a, body_b, .. } => (body_a, body_b),
+        }
+    }
+
+    /// Velocities of the bodies the solver never moves.
+    fn fixed_velocities(&self) -> (Option<Velocity>, Option<Velocity>) {
+        let (body_a, body_b) = self.bodies();
+        let (body_a, body_b) = body::read_pair(body_a, body_b);
+
+        (body_a.fixed_velocity(), body_b.fixed_velocity())
+    }
+
+    /// The current value of the coordinate and its jacobian.
+    fn evaluate(&self) -> (f32, Jacobian) {
+        let (body_a, body_b) = self.bodies();
+        let (body_a, body_b) = body::read_pair(body_a, body_b);
+
+        match self {
+            JointCoordinate::Revolute {
+                reference_angle, ..
+            } => (
+                body_b.rotation - body_a.rotation - reference_angle,
+                Jacobian {
+                    angular_a: -1.,
+                    angular_b: 1.,
+                    ..Jacobian::default()
+                },
+            ),
+            JointCoordinate::Prismatic {
+                local_anchor_a,
+                local_anchor_b,
+                local_axis,
+                ..
+            } => {
+                let anchor_a = body_a.local_to_world(*local_anchor_a);
+                let anchor_b = body_b.local_to_world(*local_anchor_b);
+                let to_anchor_a = anchor_a - body_a.center_of_gravity();
+                let to_anchor_b = anchor_b - body_b.center_of_gravity();
+                let delta = anchor_b - anchor_a;
+                let axis = rotate(*local_axis, body_a.rotation);
+
+                (
+                    axis.dot(&delta),
+                    Jacobian {
+                        linear_a: -axis,
+                        angular_a: -cross(delta + to_anchor_a, axis),
+                        linear_b: axis,
+                        angular_b: cross(to_anchor_b, axis),
+                    },
+                )
+            }
+        }
+    }
+}
+
+/// Couples two revolute or prismatic joints so that the coordinate of the first plus `ratio`
+/// times the coordinate of the second stays constant. Turning one gear turns the other, or a
+/// pinion drives a rack.
+///
+/// The joints being coupled still have to be in the world, this only adds the coupling. Gears
+/// added with `World::add_gear` are removed along with either joint, when it is removed or
+/// breaks.
+pub struct GearJoint {
+    coordinate_a: JointCoordinate,
+    coordinate_b: JointCoordinate,
+
+    ratio: f32,
+    constant: f32,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of the bodies of each coordinate, for the ones the solver never moves
+    fixed_velocities_a: (Option<Velocity>, Option<Velocity>),
+    fixed_velocities_b: (Option<Velocity>, Option<Velocity>),
+    jacobian_a: Jacobian,
+    jacobian_b: Jacobian,
+    error: f32,
+    effective_mass: f32,
+
+    // Accumulated over frames for warm starting
+    impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl GearJoint {
+    pub fn new(coordinate_a: JointCoordinate, coordinate_b: JointCoordinate, ratio: f32) -> Self {
+        let constant = coordinate_a.evaluate().0 + ratio * coordinate_b.evaluate().0;
+
+        Self {
+            coordinate_a,
+            coordinate_b,
+            ratio,
+            constant,
+            fixed_velocities_a: (None, None),
+            fixed_velocities_b: (None, None),
+            jacobian_a: Jacobian::default(),
+            jacobian_b: Jacobian::default(),
+            error: 0.,
+            effective_mass: 0.,
+            impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// Applies `impulse` along the jacobian to the bodies of a coordinate.
+    fn apply(
+        coordinate: &JointCoordinate,
+        fixed_velocities: (Option<Velocity>, Option<Velocity>),
+        jacobian: &Jacobian,
+        impulse: f32,
+    ) {
+        let (body_a, body_b) = coordinate.bodies();
+        let (mut body_a, mut body_b) = SolverBody::lock_pair(body_a, body_b, fixed_velocities);
+
+        body_a.apply_impulse(impulse * jacobian.linear_a);
+        body_a.apply_angular_impulse(impulse * jacobian.angular_a);
+        body_b.apply_impulse(impulse * jacobian.linear_b);
+        body_b.apply_angular_impulse(impulse * jacobian.angular_b);
+    }
+
+    fn speed(
+        coordinate: &JointCoordinate,
+        fixed_velocities: (Option<Velocity>, Option<Velocity>),
+        jacobian: &Jacobian,
+    ) -> f32 {
+        let (body_a, body_b) = coordinate.bodies();
+        let (body_a, body_b) = SolverBody::lock_pair(body_a, body_b, fixed_velocities);
+
+        jacobian.linear_a.dot(&body_a.velocity())
+            + jacobian.angular_a * body_a.angular_velocity()
+            + jacobian.linear_b.dot(&body_b.velocity())
+            + jacobian.angular_b * body_b.angular_velocity()
+    }
+
+    fn inv_mass(coordinate: &JointCoordinate, jacobian: &Jacobian) -> f32 {
+        let (body_a, body_b) = coordinate.bodies();
+        let (body_a, body_b) = body::read_pair(body_a, body_b);
+
+        let part = |body: &Body, linear: Vec2, angular: f32| {
+            body.inv_mass() * linear.magnitude_squared() + body.inv_inertia() * angular * angular
+        };
+
+        part(&body_a, jacobian.linear_a, jacobian.angular_a)
+            + part(&body_b, jacobian.linear_b, jacobian.angular_b)
+    }
+}
+
+impl Constraint for GearJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+        self.fixed_velocities_a = self.coordinate_a.fixed_velocities();
+        self.fixed_velocities_b = self.coordinate_b.fixed_velocities();
+
+        // The bodies of each coordinate are locked separately. That is fine since a gear joint
+        // is in the same island as every one of its bodies, so nothing else touches them.
+        let (value_a, jacobian_a) = self.coordinate_a.evaluate();
+        let (value_b, jacobian_b) = self.coordinate_b.evaluate();
+        self.jacobian_a = jacobian_a;
+        self.jacobian_b = jacobian_b;
+        self.error = value_a + self.ratio * value_b - self.constant;
+
+        let inv_mass = Self::inv_mass(&self.coordinate_a, &self.jacobian_a)
+            + self.ratio * self.ratio * Self::inv_mass(&self.coordinate_b, &self.jacobian_b);
+
+        self.effective_mass = if inv_mass > 0. { 1. / inv_mass } else { 0. };
+
+        // Warm starting
+        Self::apply(
+            &self.coordinate_a,
+            self.fixed_velocities_a,
+            &self.jacobian_a,
+            self.impulse,
+        );
+        Self::apply(
+            &self.coordinate_b,
+            self.fixed_velocities_b,
+            &self.jacobian_b,
+            self.ratio * self.impulse,
+        );
+    }
+
+    fn solve(&mut self) {
+        let speed = Self::speed(
+            &self.coordinate_a,
+            self.fixed_velocities_a,
+            &self.jacobian_a,
+        ) + self.ratio
+            * Self::speed(
+                &self.coordinate_b,
+                self.fixed_velocities_b,
+                &self.jacobian_b,
+            );
+        let bias = BIAS_FACTOR * self.inv_dt * self.error;
+
+        let impulse = -self.effective_mass * (speed + bias);
+        self.impulse += impulse;
+
+        Self::apply(
+            &self.coordinate_a,
+            self.fixed_velocities_a,
+            &self.jacobian_a,
+            impulse,
+        );
+        Self::apply(
+            &self.coordinate_b,
+            self.fixed_velocities_b,
+            &self.jacobian_b,
+            self.ratio * impulse,
+        );
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let center = |coordinate: &JointCoordinate| {
+            coordinate
+                .bodies()
+                .1
+                .read()
+                .expect("Body lock was poisoned.")
+                .center_of_gravity()
+        };
+
+        let (center_a, center_b) = (center(&self.coordinate_a), center(&self.coordinate_b));
+        let color = color::get(ColorName::Peach);
+
+        shapes.push(DebugShape::Line {
+            start: center_a,
+            end: center_b,
+            thickness: 2.,
+            color,
+        });
+        for center in [center_a, center_b] {
+            shapes.push(DebugShape::CircleLines {
+                center,
+                radius: 8.,
+                color,
+            });
+        }
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        // Reported for the moving body of the first joint
+        self.impulse * inv_dt * self.jacobian_a.linear_b
+    }
+
+    fn reaction_torque(&self, inv_dt: f32) -> f32 {
+        self.impulse * inv_dt * self.jacobian_a.angular_b
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        let (a, b) = self.coordinate_a.bodies();
+        let (c, d) = self.coordinate_b.bodies();
+
+        vec![a, b, c, d]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,183 @@
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    arena::BodyHandle, body::SharedBody, color, debug_draw::DebugShape,
+    force_generator::ForceGenerator,
+};
+
+/// Softening distance used when a scene leaves it out.
+const DEFAULT_SOFTENING: f32 = 10.;
+
+/// Acceleration towards a point that falls off with the square of the distance, like the pull of
+/// a planet.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PointAttractor {
+    pub position: Vec2,
+    /// Acceleration times the square of the distance, so the acceleration at a distance `d` is
+    /// `strength / d²`
+    pub strength: f32,
+    /// Bodies further away are not pulled at all, `None` reaches everywhere
+    #[serde(default)]
+    pub radius: Option<f32>,
+    /// Distance below which the pull stops growing, so bodies passing through the center are not
+    /// flung away. Defaults to `DEFAULT_SOFTENING`.
+    #[serde(default = "default_softening")]
+    pub softening: f32,
+}
+
+impl ForceGenerator for PointAttractor {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        for (_, body) in bodies {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            if !body.is_dynamic() {
+                continue;
+            }
+
+            let offset = self.position - body.center_of_gravity();
+            let distance = offset.magnitude();
+            if !within(distance, self.radius) || distance <= f32::EPSILON {
+                continue;
+            }
+
+            let acceleration = self.strength / distance.max(self.softening).powi(2);
+            let force = body.mass() * body.gravity_scale() * acceleration * offset / distance;
+            body.apply_force(force);
+        }
+    }
+
+    /// Only accounts for the inverse-square part, which is exact for bodies inside the radius
+    /// that keep further than the softening distance.
+    fn potential_energy(&self, bodies: &[(BodyHandle, &SharedBody)]) -> f32 {
+        bodies
+            .iter()
+            .map(|(_, body)| body.read().expect("Body lock was poisoned."))
+            .filter(|body| body.is_dynamic())
+            .map(|body| {
+                let distance = (self.position - body.center_of_gravity()).magnitude();
+                if !within(distance, self.radius) {
+                    return 0.;
+                }
+
+                -body.mass() * body.gravity_scale() * self.strength / distance.max(self.softening)
+            })
+            .sum()
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let color = color::get(ColorName::Maroon);
+
+        shapes.push(DebugShape::Circle {
+            center: self.position,
+            radius: 6.,
+            color,
+        });
+        if let Some(radius) = self.radius {
+            shapes.push(DebugShape::CircleLines {
+                center: self.position,
+                radius,
+                color,
+            });
+        }
+    }
+}
+
+/// Every one of `bodies` pulls on every other one in proportion to both their masses, for orbits
+/// and clusters. The pull on each body is scaled by its own gravity scale.
+pub struct MutualGravity {
+    bodies: Vec<BodyHandle>,
+    /// Gravitational constant
+    strength: f32,
+    radius: Option<f32>,
+    /// Distance below which the pull between two bodies stops growing
+    softening: f32,
+}
+
+impl MutualGravity {
+    pub fn new(
+        mut bodies: Vec<BodyHandle>,
+        strength: f32,
+        radius: Option<f32>,
+        softening: f32,
+    ) -> Self {
+        bodies.sort();
+        bodies.dedup();
+
+        Self {
+            bodies,
+            strength,
+            radius,
+            softening,
+        }
+    }
+}
+
+impl ForceGenerator for MutualGravity {
+    fn apply(&mut self, bodies: &[(BodyHandle, &SharedBody)], _dt: f32, _gravity: Vec2) {
+        // Read everything first so each body only has to be locked on its own
+        let selected: Vec<(&SharedBody, Vec2, f32)> = bodies
+            .iter()
+            .filter(|(handle, _)| self.bodies.binary_search(handle).is_ok())
+            .map(|(_, shared)| {
+                let body = shared.read().expect("Body lock was poisoned.");
+                (*shared, body.center_of_gravity(), body.mass())
+            })
+            .collect();
+
+        for (i, &(shared, position, mass)) in selected.iter().enumerate() {
+            let force: Vec2 = selected
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .filter_map(|(_, &(_, other_position, other_mass))| {
+                    let offset = other_position - position;
+                    let distance = offset.magnitude();
+
+                    (within(distance, self.radius) && distance > f32::EPSILON).then(|| {
+                        self.strength * mass * other_mass / distance.max(self.softening).powi(2)
+                            * offset
+                            / distance
+                    })
+                })
+                .sum();
+
+            let mut body = shared.write().expect("Body lock was poisoned.");
+            let scale = body.gravity_scale();
+            body.apply_force(scale * force);
+        }
+    }
+
+    /// Counts every pair once and ignores the gravity scales.
+    fn potential_energy(&self, bodies: &[(BodyHandle, &SharedBody)]) -> f32 {
+        let selected: Vec<(Vec2, f32)> = bodies
+            .iter()
+            .filter(|(handle, _)| self.bodies.binary_search(handle).is_ok())
+            .map(|(_, body)| {
+                let body = body.read().expect("Body lock was poisoned.");
+                (body.center_of_gravity(), body.mass())
+            })
+            .collect();
+
+        let mut energy = 0.;
+        for (i, &(position, mass)) in selected.iter().enumerate() {
+            for &(other_position, other_mass) in selected[i + 1..].iter() {
+                let distance = (other_position - position).magnitude();
+
+                if within(distance, self.radius) {
+                    energy -= self.strength * mass * other_mass / distance.max(self.softening);
+                }
+            }
+        }
+
+        energy
+    }
+}
+
+pub fn default_softening() -> f32 {
+    DEFAULT_SOFTENING
+}
+
+fn within(distance: f32, radius: Option<f32>) -> bool {
+    radius.map_or(true, |radius| distance <= radius)
+}
@@ -0,0 +1,357 @@
+use std::ops::{Add, Mul};
+
+use nalgebra_glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::body::{Damping, SharedBody};
+
+/// How the world moves its bodies forward in time.
+///
+/// Contacts and joints change velocities once per step whichever integrator is used, so the
+/// higher order ones only pay off for bodies moved by forces alone (gravity and force
+/// generators).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Integrator {
+    /// Velocity first, then the position with the new velocity
+    #[default]
+    SymplecticEuler,
+    /// Position from the old forces, then the velocity from the average of the old and new ones
+    VelocityVerlet,
+    /// Classic fourth order Runge-Kutta, evaluates the forces four times per step
+    RungeKutta4,
+}
+
+/// What an integrator steps forward for each body.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct State {
+    pub position: Vec2,
+    pub rotation: f32,
+    pub velocity: Vec2,
+    pub angular_velocity: f32,
+}
+
+impl State {
+    /// Rate of change of the state, given the accelerations at it.
+    fn derivative(&self, acceleration: Vec2, angular_acceleration: f32) -> State {
+        State {
+            position: self.velocity,
+            rotation: self.angular_velocity,
+            velocity: acceleration,
+            angular_velocity: angular_acceleration,
+        }
+    }
+}
+
+impl Add for State {
+    type Output = State;
+
+    fn add(self, other: State) -> State {
+        State {
+            position: self.position + other.position,
+            rotation: self.rotation + other.rotation,
+            velocity: self.velocity + other.velocity,
+            angular_velocity: self.angular_velocity + other.angular_velocity,
+        }
+    }
+}
+
+impl Mul<f32> for State {
+    type Output = State;
+
+    fn mul(self, scale: f32) -> State {
+        State {
+            position: self.position * scale,
+            rotation: self.rotation * scale,
+            velocity: self.velocity * scale,
+            angular_velocity: self.angular_velocity * scale,
+        }
+    }
+}
+
+impl Integrator {
+    /// Moves dynamic bodies forward by `dt`. The forces for the start of the step have to be
+    /// applied already, `reapply_forces` is called to apply them again whenever the bodies were
+    /// moved to a different state. Forces are cleared afterwards.
+    pub fn step(
+        self,
+        bodies: &[&SharedBody],
+        dt: f32,
+        gravity: Vec2,
+        default_damping: Damping,
+        mut reapply_forces: impl FnMut(),
+    ) {
+        let initial: Vec<State> = bodies
+            .iter()
+            .map(|body| body.read().expect("Body lock was poisoned.").state())
+            .collect();
+
+        // Derivatives at the current states, clearing the forces they came from
+        let derivatives = || -> Vec<State> {
+            bodies
+                .iter()
+                .map(|body| {
+                    let mut body = body.write().expect("Body lock was poisoned.");
+                    let (acceleration, angular_acceleration) = body.acceleration(gravity);
+                    body.clear_forces();
+
+                    body.state().derivative(acceleration, angular_acceleration)
+                })
+                .collect()
+        };
+
+        let mut move_to = |states: &[State]| {
+            for (body, state) in bodies.iter().zip(states) {
+                body.write()
+                    .expect("Body lock was poisoned.")
+                    .set_state(*state);
+            }
+
+            reapply_forces();
+        };
+
+        let last: Vec<State> = match self {
+            Integrator::SymplecticEuler => derivatives()
+                .into_iter()
+                .zip(initial.iter())
+                .map(|(derivative, &state)| {
+                    let velocity = state.velocity + derivative.velocity * dt;
+                    let angular_velocity =
+                        state.angular_velocity + derivative.angular_velocity * dt;
+
+                    State {
+                        position: state.position + velocity * dt,
+                        rotation: state.rotation + angular_velocity * dt,
+                        velocity,
+                        angular_velocity,
+                    }
+                })
+                .collect(),
+            Integrator::VelocityVerlet => {
+                let start = derivatives();
+                let moved: Vec<State> = initial
+                    .iter()
+                    .zip(start.iter())
+                    .map(|(&state, derivative)| State {
+                        position: state.position
+                            + state.velocity * dt
+                            + 0.5 * derivative.velocity * dt * dt,
+                        rotation: state.rotation
+                            + state.angular_velocity * dt
+                            + 0.5 * derivative.angular_velocity * dt * dt,
+                        ..state
+                    })
+                    .collect();
+
+                move_to(&moved);
+                let end = derivatives();
+
+                moved
+                    .iter()
+                    .zip(start.iter().zip(end.iter()))
+                    .map(|(&state, (start, end))| State {
+                        velocity: state.velocity + 0.5 * (start.velocity + end.velocity) * dt,
+                        angular_velocity: state.angular_velocity
+                            + 0.5 * (start.angular_velocity + end.angular_velocity) * dt,
+                        ..state
+                    })
+                    .collect()
+            }
+            Integrator::RungeKutta4 => {
+                let advance = |derivatives: &[State], scale: f32| -> Vec<State> {
+                    initial
+                        .iter()
+                        .zip(derivatives)
+                        .map(|(&state, &derivative)| state + derivative * scale)
+                        .collect()
+                };
+
+                let k1 = derivatives();
+                move_to(&advance(&k1, dt / 2.));
+                let k2 = derivatives();
+                move_to(&advance(&k2, dt / 2.));
+                let k3 = derivatives();
+                move_to(&advance(&k3, dt));
+                let k4 = derivatives();
+
+                let slopes: Vec<State> = (0..bodies.len())
+                    .map(|i| (k1[i] + k2[i] * 2. + k3[i] * 2. + k4[i]) * (1. / 6.))
+                    .collect();
+
+                advance(&slopes, dt)
+            }
+        };
+
+        for (body, state) in bodies.iter().zip(last) {
+            let mut body = body.write().expect("Body lock was poisoned.");
+            body.set_state(state);
+            body.damp(dt, default_damping);
+            body.clear_forces();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{body::Body, world::World};
+
+    const INTEGRATORS: [Integrator; 3] = [
+        Integrator::SymplecticEuler,
+        Integrator::VelocityVerlet,
+        Integrator::RungeKutta4,
+    ];
+
+    /// A unit mass moved by `force` and `gravity`, with a closed form solution for its position.
+    struct Problem {
+        start: State,
+        gravity: Vec2,
+        force: fn(Vec2) -> Vec2,
+        /// Potential energy of `force`
+        potential: fn(Vec2) -> f32,
+        exact: fn(f32) -> Vec2,
+    }
+
+    /// Largest difference to the exact position and to the starting energy.
+    struct Errors {
+        position: f32,
+        energy: f32,
+    }
+
+    fn simulate(integrator: Integrator, problem: &Problem, dt: f32, steps: usize) -> Errors {
+        let mut body = Body::test_square(Vec2::zeros());
+        body.set_state(problem.start);
+        let body = body.into_shared();
+
+        let energy = |state: State| {
+            0.5 * state.velocity.magnitude_squared() + (problem.potential)(state.position)
+                - problem.gravity.dot(&state.position)
+        };
+        let start_energy = energy(problem.start);
+
+        let apply = || {
+            let mut body = body.write().unwrap();
+            let force = (problem.force)(body.center_of_gravity());
+            body.apply_force(force);
+        };
+
+        let mut errors = Errors {
+            position: 0.,
+            energy: 0.,
+        };
+        for step in 1..=steps {
+            apply();
+            integrator.step(&[&body], dt, problem.gravity, Damping::default(), apply);
+
+            let state = body.read().unwrap().state();
+            let position_error = (state.position - (problem.exact)(step as f32 * dt)).magnitude();
+            errors.position = errors.position.max(position_error);
+            errors.energy = errors.energy.max((energy(state) - start_energy).abs());
+        }
+
+        errors
+    }
+
+    #[test]
+    fn free_fall() {
+        let problem = Problem {
+            start: State::default(),
+            gravity: Vec2::new(0., 10.),
+            force: |_| Vec2::zeros(),
+            potential: |_| 0.,
+            exact: |t| 0.5 * Vec2::new(0., 10.) * t * t,
+        };
+        let [euler, verlet, rk4] =
+            INTEGRATORS.map(|integrator| simulate(integrator, &problem, 1. / 60., 60));
+
+        // Symplectic Euler lags behind by half a step of velocity, Verlet and RK4 are exact
+        // under constant acceleration
+        assert!(euler.position < 0.1);
+        assert!(verlet.position < 1e-3);
+        assert!(rk4.position < 1e-3);
+        assert!(verlet.position < euler.position);
+        assert!(rk4.position < euler.position);
+    }
+
+    #[test]
+    fn harmonic_oscillator() {
+        // Unit stiffness and mass, so the angular frequency is one
+        let problem = Problem {
+            start: State {
+                position: Vec2::new(1., 0.),
+                ..State::default()
+            },
+            gravity: Vec2::zeros(),
+            force: |position| -position,
+            potential: |position| 0.5 * position.magnitude_squared(),
+            exact: |t| Vec2::new(t.cos(), 0.),
+        };
+        let [euler, verlet, rk4] =
+            INTEGRATORS.map(|integrator| simulate(integrator, &problem, 0.1, 100));
+
+        assert!(euler.position < 0.1);
+        assert!(verlet.position < 1e-2);
+        assert!(rk4.position < 1e-3);
+        assert!(verlet.position < euler.position);
+        assert!(rk4.position < euler.position);
+
+        assert!(euler.energy < 0.05);
+        assert!(verlet.energy < 5e-3);
+        assert!(rk4.energy < 1e-3);
+        assert!(verlet.energy < euler.energy);
+        assert!(rk4.energy < euler.energy);
+    }
+
+    #[test]
+    fn circular_orbit() {
+        // Unit gravitational parameter, radius and speed, so one orbit takes 2π
+        let problem = Problem {
+            start: State {
+                position: Vec2::new(1., 0.),
+                velocity: Vec2::new(0., 1.),
+                ..State::default()
+            },
+            gravity: Vec2::zeros(),
+            force: |position| -position / position.magnitude().powi(3),
+            potential: |position| -1. / position.magnitude(),
+            exact: |t| Vec2::new(t.cos(), t.sin()),
+        };
+        let dt = 0.01;
+        let steps = (std::f32::consts::TAU / dt) as usize;
+        let [euler, verlet, rk4] =
+            INTEGRATORS.map(|integrator| simulate(integrator, &problem, dt, steps));
+
+        assert!(euler.position < 0.05);
+        assert!(verlet.position < 5e-3);
+        assert!(rk4.position < 1e-3);
+        assert!(verlet.position < euler.position);
+        assert!(rk4.position < euler.position);
+
+        assert!(euler.energy < 1e-2);
+        assert!(verlet.energy < 1e-3);
+        assert!(rk4.energy < 1e-3);
+    }
+
+    #[test]
+    fn applied_forces_last_the_whole_step() {
+        let dt = 1. / 60.;
+
+        for integrator in INTEGRATORS {
+            let mut world = World::new(10);
+            world.set_integrator(integrator);
+            let handle = world.add_body(Body::test_square(Vec2::zeros()));
+
+            world
+                .get_mut(handle)
+                .unwrap()
+                .apply_force(Vec2::new(60., 0.));
+            world.step(dt);
+
+            // A constant force of 60 on a unit mass, over a sixtieth of a second
+            let velocity = world.get(handle).unwrap().velocity();
+            assert!(
+                (velocity - Vec2::new(1., 0.)).magnitude() < 1e-4,
+                "{integrator:?} ended up at {velocity:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::{cmp::Reverse, collections::HashMap, sync::Arc};
+
+use rayon::prelude::*;
+
+use crate::{body::SharedBody, constraints::Constraint};
+
+/// A group of constraints that share no dynamic bodies with any other island. Islands can be
+/// solved independently of each other, which is what lets them run in parallel.
+pub struct Island<'a> {
+    constraints: Vec<&'a mut dyn Constraint>,
+}
+
+impl<'a> Island<'a> {
+    pub fn solve(&mut self, inv_dt: f32, iteration_count: usize) {
+        for constraint in self.constraints.iter_mut() {
+            constraint.pre_solve(inv_dt);
+        }
+
+        for _ in 0..iteration_count {
+            for constraint in self.constraints.iter_mut() {
+                constraint.solve();
+            }
+        }
+    }
+
+    /// Solves one substep of a sub-stepped world, with the bias. A single pass is enough since
+    /// the substeps themselves take the place of the iterations.
+    pub fn solve_substep(&mut self, inv_h: f32, first: bool) {
+        for constraint in self.constraints.iter_mut() {
+            constraint.pre_solve_substep(inv_h, first);
+        }
+
+        for constraint in self.constraints.iter_mut() {
+            constraint.solve();
+        }
+    }
+
+    pub fn relax(&mut self) {
+        for constraint in self.constraints.iter_mut() {
+            constraint.relax();
+        }
+    }
+
+    pub fn apply_restitution(&mut self) {
+        for constraint in self.constraints.iter_mut() {
+            constraint.apply_restitution();
+        }
+    }
+
+    pub fn constraint_count(&self) -> usize {
+        self.constraints.len()
+    }
+
+    /// Partitions the constraints into islands by connecting the dynamic bodies they act on.
+    /// Static and kinematic bodies do not join islands together since the solver never moves them.
+    ///
+    /// Constraints that only act on such bodies are dropped as there is nothing to solve.
+    pub fn build<'b>(
+        bodies: impl Iterator<Item = &'b SharedBody>,
+        constraints: Vec<&'a mut dyn Constraint>,
+    ) -> Vec<Self> {
+        let bodies: Vec<&SharedBody> = bodies.collect();
+
+        let indices: HashMap<_, _> = bodies
+            .iter()
+            .enumerate()
+            .map(|(i, &body)| (Arc::as_ptr(body), i))
+            .collect();
+
+        let is_dynamic: Vec<bool> = bodies
+            .iter()
+            .map(|body| body.read().expect("Body lock was poisoned.").is_dynamic())
+            .collect();
+
+        let mut sets = DisjointSet::new(bodies.len());
+
+        let dynamic_bodies: Vec<Vec<usize>> = constraints
+            .iter()
+            .map(|constraint| {
+                constraint
+                    .bodies()
+                    .into_iter()
+                    .filter_map(|body| indices.get(&Arc::as_ptr(body)).copied())
+                    .filter(|&i| is_dynamic[i])
+                    .collect()
+            })
+            .collect();
+
+        for connected in dynamic_bodies.iter() {
+            for pair in connected.windows(2) {
+                sets.union(pair[0], pair[1]);
+            }
+        }
+
+        // Islands are created in the order their first constraint shows up so that the result
+        // does not depend on hashing.
+        let mut islands: Vec<Self> = vec![];
+        let mut island_of_root: HashMap<usize, usize> = HashMap::new();
+
+        for (constraint, connected) in constraints.into_iter().zip(dynamic_bodies) {
+            let Some(&first) = connected.first() else {
+                continue;
+            };
+
+            let root = sets.find(first);
+            let island = *island_of_root.entry(root).or_insert_with(|| {
+                islands.push(Island {
+                    constraints: vec![],
+                });
+                islands.len() - 1
+            });
+
+            islands[island].constraints.push(constraint);
+        }
+
+        // Bigger islands first so that the pool does not pick them up last
+        islands.sort_by_key(|island| Reverse(island.constraint_count()));
+
+        islands
+    }
+
+    /// Solves every island, spreading them over the available cores.
+    pub fn solve_parallel(islands: &mut [Self], inv_dt: f32, iteration_count: usize) {
+        Self::run_parallel(islands, |island| island.solve(inv_dt, iteration_count));
+    }
+
+    /// Runs `work` on every island on the thread pool. The pool lives for the whole program, so
+    /// this can be called several times per step without spawning any threads.
+    pub fn run_parallel(islands: &mut [Self], work: impl Fn(&mut Self) + Sync + Send) {
+        islands.par_iter_mut().for_each(work);
+    }
+}
+
+/// Union-find over body indices.
+struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+        }
+    }
+
+    fn find(&mut self, index: usize) -> usize {
+        let mut root = index;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // Path compression
+        let mut current = index;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+        if a != b {
+            self.parents[b] = a;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::Vec2;
+
+    use super::*;
+    use crate::{body::Body, distance_joint::DistanceJoint};
+
+    #[test]
+    fn unions_join_sets() {
+        let mut sets = DisjointSet::new(5);
+        sets.union(0, 1);
+        sets.union(3, 4);
+        sets.union(1, 4);
+
+        let root = sets.find(0);
+        assert!([1, 3, 4].iter().all(|&i| sets.find(i) == root));
+        assert_ne!(sets.find(2), root);
+    }
+
+    #[test]
+    fn find_compresses_paths() {
+        let mut sets = DisjointSet::new(4);
+        sets.union(2, 3);
+        sets.union(1, 2);
+        sets.union(0, 1);
+
+        let root = sets.find(3);
+        assert!(sets.parents.iter().all(|&parent| parent == root));
+    }
+
+    fn joint(a: &SharedBody, b: &SharedBody) -> DistanceJoint {
+        DistanceJoint::new(a.clone(), Vec2::zeros(), b.clone(), Vec2::zeros(), 10., 10.)
+    }
+
+    #[test]
+    fn connected_constraints_share_an_island() {
+        let bodies: Vec<SharedBody> = (0..5)
+            .map(|i| Body::test_square(Vec2::new(10. * i as f32, 0.)).into_shared())
+            .collect();
+
+        // A chain of three bodies and a separate pair
+        let mut joints = [
+            joint(&bodies[0], &bodies[1]),
+            joint(&bodies[3], &bodies[4]),
+            joint(&bodies[1], &bodies[2]),
+        ];
+        let constraints: Vec<&mut dyn Constraint> = joints
+            .iter_mut()
+            .map(|joint| joint as &mut dyn Constraint)
+            .collect();
+
+        let islands = Island::build(bodies.iter(), constraints);
+
+        // Largest island first
+        let sizes: Vec<usize> = islands.iter().map(Island::constraint_count).collect();
+        assert_eq!(sizes, vec![2, 1]);
+    }
+}
@@ -1,24 +1,52 @@
-use body::Body;
+use arena::BodyHandle;
+use body::Damping;
 use catppuccin::ColorName;
-use collision_constraint::CollisionConstraint;
-use constraints::Constraint;
+use events::Event;
+use integrator::Integrator;
 use nalgebra_glm::{vec2, Vec2};
+use physics_thread::{Command, Controls, PhysicsThread};
 use raylib::prelude::*;
+use scene::Scene;
 use serde::{Deserialize, Serialize};
 use std::{
-    borrow::BorrowMut,
-    cell::RefCell,
-    collections::HashMap,
+    collections::VecDeque,
     fs::{self},
-    rc::Rc,
 };
+use vehicle::Vehicle;
+use world::World;
 
+mod arena;
 mod body;
 mod collision_constraint;
+mod collision_filter;
 mod constraints;
 mod contact_point;
+mod debug_draw;
+mod distance_joint;
+mod events;
+mod fluid;
+mod force_generator;
+mod gear_joint;
+mod gravity;
+mod integrator;
+mod island;
+mod math;
+mod narrowphase;
+mod physics_thread;
 mod plane;
 mod polygon;
+mod pre_solve;
+mod prismatic_joint;
+mod pulley_joint;
+mod revolute_joint;
+mod scene;
+mod snapshot;
+mod spring_joint;
+mod target_joint;
+mod vehicle;
+mod weld_joint;
+mod wheel_joint;
+mod world;
 
 mod color;
 
@@ -43,6 +71,33 @@ pub struct EngineConfig {
     display_width: i32,
     display_height: i32,
     iteration_count: usize,
+    /// Run the world on its own thread instead of stepping it in between frames
+    #[serde(default)]
+    threaded: bool,
+    /// Steps per second of the physics thread
+    #[serde(default = "default_physics_rate")]
+    physics_rate: f32,
+    /// Damping of the bodies that do not set their own
+    #[serde(default)]
+    damping: Damping,
+    #[serde(default)]
+    integrator: Integrator,
+    /// Solve in this many substeps per step instead of all at once
+    #[serde(default)]
+    substep_count: Option<usize>,
+}
+
+fn default_physics_rate() -> f32 {
+    60.
+}
+
+/// How many of the most recent events are shown on screen
+const EVENT_LOG_LENGTH: usize = 8;
+
+/// Where the world is being stepped.
+enum Simulation {
+    Local(World, Controls),
+    Threaded(PhysicsThread),
 }
 
 pub struct Engine {
@@ -50,11 +105,13 @@ pub struct Engine {
     thread: RaylibThread,
     camera: Camera2D,
 
-    bodies: Vec<Rc<RefCell<Body>>>,
+    simulation: Simulation,
+    controlled_body: Option<BodyHandle>,
+    /// Takes over the keyboard from `controlled_body` when the scene has a vehicle
+    controlled_vehicle: Option<Vehicle>,
 
-    general_constraints: Vec<Box<dyn Constraint>>,
-    collision_map: HashMap<(usize, usize), CollisionConstraint>,
-    iteration_count: usize,
+    /// The most recent events of the world, newest last
+    event_log: VecDeque<String>,
 }
 
 impl Engine {
@@ -65,6 +122,14 @@ impl Engine {
             .title("Physics Jank")
             .build();
 
+        let (world, controlled_body, controlled_vehicle) = Self::load_simulation(&config);
+
+        let simulation = if config.threaded {
+            Simulation::Threaded(PhysicsThread::spawn(world, 1. / config.physics_rate))
+        } else {
+            Simulation::Local(world, Controls::default())
+        };
+
         let mut engine = Self {
             handle,
             thread,
@@ -74,135 +139,181 @@ impl Engine {
                 rotation: 0.,
                 zoom: 1.,
             },
-            bodies: vec![],
-            general_constraints: vec![],
-            collision_map: HashMap::default(),
-            iteration_count: config.iteration_count,
+            simulation,
+            controlled_body,
+            controlled_vehicle,
+            event_log: VecDeque::new(),
         };
 
-        engine.load_simulation(config);
-
         while !engine.handle.window_should_close() {
             let delta_time = engine.handle.get_frame_time();
 
-            engine.check_collisions(delta_time);
-            engine.test_controller();
-            engine.resolve_collisions(delta_time);
-            engine.integrate(delta_time);
-            engine.draw();
-        }
-    }
+            if engine.controlled_vehicle.is_some() {
+                engine.vehicle_controller();
+            } else {
+                engine.test_controller();
+            }
+            engine.mouse_controller();
 
-    fn load_simulation(&mut self, config: EngineConfig) {
-        let config_file =
-            fs::read_to_string(config.setup_file).expect("Could not find the setup file.");
+            let events = match &mut engine.simulation {
+                Simulation::Local(world, _) => {
+                    world.step(delta_time);
+                    world.events().to_vec()
+                }
+                Simulation::Threaded(physics) => physics.events(),
+            };
 
-        let mut bodies: Vec<Body> =
-            serde_json::from_str(&config_file).expect("Unable to read the setup file");
+            // Contacts that carry on would push everything else out of the log
+            for event in events {
+                if !matches!(event, Event::ContactPersist(_)) {
+                    engine.event_log.push_back(event.to_string());
+                }
+            }
 
-        for body in bodies.iter_mut() {
-            body.borrow_mut().construct_collider();
+            while engine.event_log.len() > EVENT_LOG_LENGTH {
+                engine.event_log.pop_front();
+            }
+
+            engine.draw();
         }
+    }
 
-        self.bodies = bodies
-            .into_iter()
-            .map(|x| Rc::new(RefCell::new(x)))
-            .collect();
+    fn load_simulation(config: &EngineConfig) -> (World, Option<BodyHandle>, Option<Vehicle>) {
+        let mut world = World::new(config.iteration_count);
+        world.set_default_damping(config.damping);
+        world.set_integrator(config.integrator);
+        world.set_substep_count(config.substep_count);
+        let handles = Scene::load(&config.setup_file).build(&mut world);
+
+        (
+            world,
+            handles.bodies.first().copied(),
+            handles.vehicles.into_iter().next(),
+        )
     }
 
-    fn check_collisions(&mut self, dt: f32) {
-        let length = self.bodies.len();
-        for (i, body_cell) in self.bodies[0..length - 1].iter().enumerate() {
-            for (j, other_body_cell) in self.bodies[i + 1..length].iter().enumerate() {
-                let body = (**body_cell).borrow();
-                let other_body = (**other_body_cell).borrow();
-                let sat_output = body.check_collision(&other_body, dt);
-
-                if let Some((is_reference, normal, ..)) = sat_output {
-                    let (incident_body, reference_body) = if is_reference {
-                        (other_body_cell, body_cell)
-                    } else {
-                        (body_cell, other_body_cell)
-                    };
-
-                    let new_manifold = CollisionConstraint::generate_manifold(
-                        normal,
-                        &incident_body.borrow(),
-                        &reference_body.borrow(),
-                    );
-
-                    match self.collision_map.get_mut(&(i, j)) {
-                        Some(constraint) => {
-                            constraint.update_manifold(new_manifold);
-                        }
-                        None => {
-                            self.collision_map.insert(
-                                (i, j),
-                                CollisionConstraint::new(
-                                    new_manifold,
-                                    incident_body.clone(),
-                                    reference_body.clone(),
-                                ),
-                            );
-                        }
-                    }
-                } else {
-                    // Remove collisions that did not happen
-                    self.collision_map.remove(&(i, j));
+    fn draw(&mut self) {
+        let draw = &mut self.handle.begin_drawing(&self.thread);
+        draw.clear_background(color::get(ColorName::Base));
+
+        let energy = {
+            let mut draw2d = draw.begin_mode2D(self.camera);
+
+            match &self.simulation {
+                Simulation::Local(world, _) => {
+                    world.draw(&mut draw2d);
+                    world.energy()
+                }
+                Simulation::Threaded(physics) => {
+                    let snapshot = physics.latest_snapshot();
+                    snapshot.draw(&mut draw2d);
+                    snapshot.energy
                 }
             }
+        };
+
+        for (i, line) in self.event_log.iter().enumerate() {
+            draw.draw_text(
+                line,
+                10,
+                10 + 20 * i as i32,
+                16,
+                color::get(ColorName::Text),
+            );
         }
-    }
 
-    fn resolve_collisions(&mut self, dt: f32) {
-        let inv_dt = 1. / dt;
+        // Below the event log, so the drift of the integrator can be watched
+        draw.draw_text(
+            &format!("Energy: {energy:.1}"),
+            10,
+            10 + 20 * EVENT_LOG_LENGTH as i32,
+            16,
+            color::get(ColorName::Subtext0),
+        );
+    }
 
-        for general_constraint in self.general_constraints.iter_mut() {
-            general_constraint.pre_solve(inv_dt);
+    fn send(&mut self, command: Command) {
+        match &mut self.simulation {
+            Simulation::Local(world, controls) => command.apply(world, controls),
+            Simulation::Threaded(physics) => physics.send(command),
         }
+    }
 
-        for (.., contact_constraint) in self.collision_map.iter_mut() {
-            contact_constraint.pre_solve(inv_dt);
+    /// Click and drag to pull bodies around, right click to set off an explosion and middle click
+    /// to remove a body.
+    fn mouse_controller(&mut self) {
+        let mouse = self.handle.get_mouse_position();
+        let point = self.handle.get_screen_to_world2D(mouse, self.camera);
+        let point = vec2(point.x, point.y);
+
+        if self
+            .handle
+            .is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT)
+        {
+            self.send(Command::Grab { point });
+        } else if self
+            .handle
+            .is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT)
+        {
+            self.send(Command::Drag { point });
+        } else if self
+            .handle
+            .is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT)
+        {
+            self.send(Command::Release);
         }
 
-        // Solving the constraints
-        for _ in 0..self.iteration_count {
-            for constraint in self.general_constraints.iter_mut() {
-                constraint.solve();
-            }
-
-            for (.., constraint) in self.collision_map.iter_mut() {
-                constraint.solve();
-            }
+        if self
+            .handle
+            .is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT)
+        {
+            self.send(Command::Explode { point });
         }
-    }
 
-    fn integrate(&mut self, dt: f32) {
-        for body in self.bodies.iter_mut() {
-            let mut body = (**body).borrow_mut();
-            body.integrate(dt);
+        if self
+            .handle
+            .is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE)
+        {
+            self.send(Command::Remove { point });
         }
     }
 
-    fn draw(&mut self) {
-        let draw = &mut self.handle.begin_drawing(&self.thread);
-        draw.clear_background(color::get(ColorName::Base));
+    /// D and A for throttle forwards and backwards, space to brake.
+    fn vehicle_controller(&mut self) {
+        let Some(vehicle) = self.controlled_vehicle.clone() else {
+            return;
+        };
 
-        let mut draw2d = draw.begin_mode2D(self.camera);
+        let handle = &self.handle;
+        let mut throttle = 0.;
 
-        for body in self.bodies.iter() {
-            body.borrow().draw(&mut draw2d);
+        if handle.is_key_down(KeyboardKey::KEY_D) {
+            throttle += 1.;
         }
 
-        for (.., constraint) in self.collision_map.iter_mut() {
-            constraint.draw(&mut draw2d);
+        if handle.is_key_down(KeyboardKey::KEY_A) {
+            throttle -= 1.;
         }
+
+        let brake = if handle.is_key_down(KeyboardKey::KEY_SPACE) {
+            1.
+        } else {
+            0.
+        };
+
+        self.send(Command::Drive {
+            vehicle,
+            throttle,
+            brake,
+        });
     }
 
     fn test_controller(&mut self) {
-        let handle = &self.handle;
-        let mut body = (*self.bodies[0]).borrow_mut();
+        let Some(body) = self.controlled_body else {
+            return;
+        };
 
+        let handle = &self.handle;
         let mut impulse = vec2(0., 0.);
 
         if handle.is_key_down(KeyboardKey::KEY_W) {
@@ -221,20 +332,26 @@ impl Engine {
             impulse += Vec2::new(1., 0.);
         }
 
-        if impulse.magnitude() > 0. {
-            body.apply_impulse(impulse.normalize());
-        }
-
-        let mut impulse = 0.;
+        let mut angular_impulse = 0.;
 
         if handle.is_key_down(KeyboardKey::KEY_J) {
-            impulse += 0.05;
+            angular_impulse += 0.05;
         }
 
         if handle.is_key_down(KeyboardKey::KEY_K) {
-            impulse += -0.05;
+            angular_impulse += -0.05;
+        }
+
+        if impulse.magnitude() > 0. {
+            self.send(Command::Impulse {
+                body,
+                impulse: impulse.normalize(),
+            });
         }
 
-        body.apply_angular_impulse(impulse);
+        self.send(Command::AngularImpulse {
+            body,
+            impulse: angular_impulse,
+        });
     }
 }
@@ -0,0 +1,32 @@
+use nalgebra_glm::Vec2;
+
+/// 2D cross product, the z component of the 3D one.
+pub fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Velocity of a point at `offset` from the center of rotation due to the angular velocity.
+pub fn angular_to_tangent(angular_velocity: f32, offset: Vec2) -> Vec2 {
+    Vec2::new(-angular_velocity * offset.y, angular_velocity * offset.x)
+}
+
+/// Solves `k * x = b` for a symmetric 2x2 matrix given by its entries. Returns zero if `k` is
+/// singular.
+pub fn solve_symmetric_2x2(k11: f32, k12: f32, k22: f32, b: Vec2) -> Vec2 {
+    let det = k11 * k22 - k12 * k12;
+    if det == 0. {
+        return Vec2::default();
+    }
+
+    let inv_det = 1. / det;
+    Vec2::new(
+        inv_det * (k22 * b.x - k12 * b.y),
+        inv_det * (k11 * b.y - k12 * b.x),
+    )
+}
+
+/// Rotates `v` by `angle` radians.
+pub fn rotate(v: Vec2, angle: f32) -> Vec2 {
+    let (sin, cos) = angle.sin_cos();
+    Vec2::new(cos * v.x - sin * v.y, sin * v.x + cos * v.y)
+}
@@ -0,0 +1,137 @@
+use nalgebra_glm::Vec2;
+use rayon::prelude::*;
+
+use crate::{
+    collision_constraint::CollisionConstraint, collision_filter::CollisionFilter,
+    contact_point::ContactPoint, polygon::Polygon,
+};
+
+/// Read-only world space data of a collider for one step. The narrowphase only ever looks at
+/// these, so it does not need to lock any bodies.
+pub struct ColliderProxy {
+    polygon: Polygon,
+    filter: CollisionFilter,
+    sensor: bool,
+    min: Vec2,
+    max: Vec2,
+}
+
+impl ColliderProxy {
+    pub fn new(polygon: Polygon, filter: CollisionFilter, sensor: bool) -> Self {
+        let (min, max) = polygon.bounds();
+        Self {
+            polygon,
+            filter,
+            sensor,
+            min,
+            max,
+        }
+    }
+
+    pub fn is_sensor(&self) -> bool {
+        self.sensor
+    }
+
+    pub fn overlaps(&self, other: &ColliderProxy) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
+
+/// The output of the narrowphase for a single pair.
+pub struct PairCollision {
+    /// Whether the first body of the pair owns the reference face
+    pub first_is_reference: bool,
+    pub manifold: Vec<ContactPoint>,
+}
+
+/// Broadphase: sweep and prune along the x axis. Pairs whose filters rule out a collision are
+/// skipped, as are pairs of two sensors. The pairs are returned as `(i, j)` with `i < j` sorted
+/// in ascending order.
+pub fn find_pairs(proxies: &[Option<ColliderProxy>]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..proxies.len())
+        .filter(|&i| proxies[i].is_some())
+        .collect();
+
+    order.sort_by(|&a, &b| {
+        let a = proxies[a].as_ref().map_or(0., |proxy| proxy.min.x);
+        let b = proxies[b].as_ref().map_or(0., |proxy| proxy.min.x);
+        a.total_cmp(&b)
+    });
+
+    let mut pairs = vec![];
+
+    for (n, &i) in order.iter().enumerate() {
+        let Some(proxy) = &proxies[i] else {
+            continue;
+        };
+
+        for &j in order[n + 1..].iter() {
+            let Some(other) = &proxies[j] else {
+                continue;
+            };
+
+            if other.min.x > proxy.max.x {
+                break;
+            }
+
+            if proxy.filter.should_collide(&other.filter)
+                && !(proxy.sensor && other.sensor)
+                && proxy.overlaps(other)
+            {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+    }
+
+    pairs.sort();
+    pairs
+}
+
+/// Runs SAT and manifold generation for every pair on the thread pool. The results are
+/// collected back in order, so they are the same no matter how the pairs were spread.
+pub fn collide_pairs(
+    proxies: &[Option<ColliderProxy>],
+    pairs: &[(usize, usize)],
+    dt: f32,
+) -> Vec<Option<PairCollision>> {
+    pairs
+        .par_iter()
+        .map(|&(i, j)| collide(proxies[i].as_ref()?, proxies[j].as_ref()?, dt))
+        .collect()
+}
+
+/// Exact overlap test for pairs that only need to know whether they touch, like sensors. There
+/// are few enough of these that they are not worth spreading over threads.
+pub fn touching_pairs(
+    proxies: &[Option<ColliderProxy>],
+    pairs: &[(usize, usize)],
+    dt: f32,
+) -> Vec<bool> {
+    pairs
+        .iter()
+        .map(|&(i, j)| match (&proxies[i], &proxies[j]) {
+            (Some(first), Some(second)) => {
+                first.polygon.check_collision(&second.polygon, dt).is_some()
+            }
+            _ => false,
+        })
+        .collect()
+}
+
+fn collide(first: &ColliderProxy, second: &ColliderProxy, dt: f32) -> Option<PairCollision> {
+    let (first_is_reference, normal, ..) = first.polygon.check_collision(&second.polygon, dt)?;
+
+    let manifold = if first_is_reference {
+        CollisionConstraint::generate_manifold(normal, &second.polygon, &first.polygon)
+    } else {
+        CollisionConstraint::generate_manifold(normal, &first.polygon, &second.polygon)
+    };
+
+    Some(PairCollision {
+        first_is_reference,
+        manifold,
+    })
+}
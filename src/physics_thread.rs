@@ -0,0 +1,220 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use nalgebra_glm::Vec2;
+
+use crate::{
+    arena::BodyHandle,
+    events::Event,
+    force_generator::Explosion,
+    snapshot::WorldSnapshot,
+    target_joint::TargetJoint,
+    vehicle::Vehicle,
+    world::{JointHandle, World},
+};
+
+const EXPLOSION_RADIUS: f32 = 300.;
+const EXPLOSION_IMPULSE: f32 = 500.;
+
+/// Input from the viewer, applied by the physics thread before its next step.
+pub enum Command {
+    Impulse {
+        body: BodyHandle,
+        impulse: Vec2,
+    },
+    AngularImpulse {
+        body: BodyHandle,
+        impulse: f32,
+    },
+    /// Starts dragging the body under the point, if there is one
+    Grab {
+        point: Vec2,
+    },
+    /// Moves the target of the current drag
+    Drag {
+        point: Vec2,
+    },
+    Release,
+    /// Sets off an explosion centered on the point
+    Explode {
+        point: Vec2,
+    },
+    /// Takes the body under the point out of the world, if there is one
+    Remove {
+        point: Vec2,
+    },
+    Drive {
+        vehicle: Vehicle,
+        throttle: f32,
+        brake: f32,
+    },
+}
+
+/// Viewer state that lives next to the world, wherever it is being stepped.
+#[derive(Default)]
+pub struct Controls {
+    grab: Option<JointHandle>,
+}
+
+impl Command {
+    pub fn apply(self, world: &mut World, controls: &mut Controls) {
+        match self {
+            Command::Impulse { body, impulse } => {
+                if let Some(mut body) = world.get_mut(body) {
+                    body.apply_impulse(impulse);
+                }
+            }
+            Command::AngularImpulse { body, impulse } => {
+                if let Some(mut body) = world.get_mut(body) {
+                    body.apply_angular_impulse(impulse);
+                }
+            }
+            Command::Grab { point } => {
+                if let Some(joint) = controls.grab.take() {
+                    world.remove_constraint(joint);
+                }
+
+                let Some(body) = world
+                    .body_at(point)
+                    .and_then(|body| world.shared_body(body))
+                else {
+                    return;
+                };
+
+                let mass = body.read().expect("Body lock was poisoned.").mass();
+                let joint = TargetJoint::new(body, point, 1000. * mass, 5., 0.7);
+                controls.grab = Some(world.add_constraint(Box::new(joint)));
+            }
+            Command::Drag { point } => {
+                if let Some(joint) = controls
+                    .grab
+                    .and_then(|joint| world.joint_mut::<TargetJoint>(joint))
+                {
+                    joint.set_target(point);
+                }
+            }
+            Command::Release => {
+                if let Some(joint) = controls.grab.take() {
+                    world.remove_constraint(joint);
+                }
+            }
+            Command::Explode { point } => {
+                world.add_force_generator(Box::new(Explosion::new(
+                    point,
+                    EXPLOSION_RADIUS,
+                    EXPLOSION_IMPULSE,
+                )));
+            }
+            Command::Remove { point } => {
+                if let Some(body) = world.body_at(point) {
+                    // Removing the body also removes the drag joint on it
+                    world.remove_body(body);
+                }
+            }
+            Command::Drive {
+                vehicle,
+                throttle,
+                brake,
+            } => vehicle.drive(world, throttle, brake),
+        }
+    }
+}
+
+/// Runs a world on its own thread at a fixed rate. After every step a snapshot is published
+/// which the viewer can pick up whenever it gets around to drawing.
+pub struct PhysicsThread {
+    commands: Sender<Command>,
+    latest: Arc<Mutex<Arc<WorldSnapshot>>>,
+    /// Events of every step, so none are missed when the viewer skips snapshots
+    events: Receiver<Event>,
+    running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PhysicsThread {
+    pub fn spawn(mut world: World, timestep: f32) -> Self {
+        let (commands, receiver) = mpsc::channel::<Command>();
+        let (event_sender, events) = mpsc::channel::<Event>();
+        let latest = Arc::new(Mutex::new(Arc::new(world.snapshot())));
+        let running = Arc::new(AtomicBool::new(true));
+
+        let thread = {
+            let latest = latest.clone();
+            let running = running.clone();
+
+            thread::spawn(move || {
+                let mut controls = Controls::default();
+                let tick = Duration::from_secs_f32(timestep);
+                let mut next_tick = Instant::now();
+
+                while running.load(Ordering::Relaxed) {
+                    for command in receiver.try_iter() {
+                        command.apply(&mut world, &mut controls);
+                    }
+
+                    world.step(timestep);
+
+                    for event in world.events() {
+                        // The viewer going away is handled by `running`
+                        let _ = event_sender.send(event.clone());
+                    }
+
+                    let snapshot = Arc::new(world.snapshot());
+                    *latest.lock().expect("Snapshot lock was poisoned.") = snapshot;
+
+                    next_tick += tick;
+                    let now = Instant::now();
+                    if next_tick > now {
+                        thread::sleep(next_tick - now);
+                    } else {
+                        // Running behind, don't try to catch up on the missed steps
+                        next_tick = now;
+                    }
+                }
+            })
+        };
+
+        Self {
+            commands,
+            latest,
+            events,
+            running,
+            thread: Some(thread),
+        }
+    }
+
+    pub fn send(&self, command: Command) {
+        // The only way this fails is if the physics thread is gone, in which case there is
+        // nothing left to apply the command to.
+        let _ = self.commands.send(command);
+    }
+
+    /// Events of the steps since this was last called.
+    pub fn events(&self) -> Vec<Event> {
+        self.events.try_iter().collect()
+    }
+
+    pub fn latest_snapshot(&self) -> Arc<WorldSnapshot> {
+        self.latest
+            .lock()
+            .expect("Snapshot lock was poisoned.")
+            .clone()
+    }
+}
+
+impl Drop for PhysicsThread {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use nalgebra_glm::{vec2, Vec2};
-use raylib::prelude::*;
+use raylib::color::Color;
+
+use crate::debug_draw::DebugShape;
 
 #[derive(Clone)]
 pub struct Plane {
@@ -84,12 +86,12 @@ impl Plane {
         (self.start() + self.end()) * (1. / 2.)
     }
 
-    pub fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>, color: &Color) {
-        handle.draw_line_ex(
-            Vector2::new(self.start.x, self.start.y),
-            Vector2::new(self.end.x, self.end.y),
-            5.,
-            color,
-        );
+    pub fn draw(&self, shapes: &mut Vec<DebugShape>, color: &Color) {
+        shapes.push(DebugShape::Line {
+            start: self.start,
+            end: self.end,
+            thickness: 5.,
+            color: *color,
+        });
     }
 }
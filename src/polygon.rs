@@ -6,9 +6,9 @@ use raylib::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::plane::Plane;
+use crate::{math::cross, plane::Plane};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Polygon {
     points: Vec<Vec2>,
     position: Vec2,
@@ -144,6 +144,80 @@ impl Polygon {
         handle.draw_line_v(rpoints[0], rpoints[self.points.len() - 1], color);
     }
 
+    /// Assumes the polygon is convex, like SAT does.
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        (0..self.points.len()).all(|i| {
+            let plane = self.get_plane(i);
+            plane.get_normal().dot(&(point - plane.start())) <= 0.
+        })
+    }
+
+    /// Axis aligned bounds of the points as (min, max).
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        self.points.iter().fold(
+            (
+                vec2(f32::INFINITY, f32::INFINITY),
+                vec2(-f32::INFINITY, -f32::INFINITY),
+            ),
+            |(min, max), point| {
+                (
+                    vec2(min.x.min(point.x), min.y.min(point.y)),
+                    vec2(max.x.max(point.x), max.y.max(point.y)),
+                )
+            },
+        )
+    }
+
+    /// The part of the polygon behind `plane`, on the side its normal points away from. Returns
+    /// `None` if nothing is left.
+    pub fn clip(&self, plane: &Plane) -> Option<Polygon> {
+        let (normal, c) = plane.get_normal_form();
+        let distance = |point: Vec2| normal.dot(&point) - c;
+
+        let mut points = vec![];
+        for i in 0..self.points.len() {
+            let start = self.points[i];
+            let end = self.points[self.increment_side(i)];
+            let (start_distance, end_distance) = (distance(start), distance(end));
+
+            if start_distance <= 0. {
+                points.push(start);
+            }
+
+            // The edge crosses the plane
+            if (start_distance <= 0.) != (end_distance <= 0.) {
+                let t = start_distance / (start_distance - end_distance);
+                points.push(start + t * (end - start));
+            }
+        }
+
+        (points.len() >= 3).then(|| Polygon { points, ..*self })
+    }
+
+    /// Area enclosed by the points along with its centroid, regardless of the winding order.
+    pub fn area_and_centroid(&self) -> (f32, Vec2) {
+        let Some(&origin) = self.points.first() else {
+            return (0., Vec2::default());
+        };
+
+        // Fan of triangles from the first point
+        let mut area = 0.;
+        let mut centroid = Vec2::default();
+        for window in self.points[1..].windows(2) {
+            let (a, b) = (window[0] - origin, window[1] - origin);
+            let triangle_area = cross(a, b) / 2.;
+
+            area += triangle_area;
+            centroid += triangle_area * (a + b) / 3.;
+        }
+
+        if area.abs() <= f32::EPSILON {
+            return (0., origin);
+        }
+
+        (area.abs(), origin + centroid / area)
+    }
+
     pub fn point_count(&self) -> usize {
         self.points.len()
     }
@@ -160,3 +234,71 @@ impl Polygon {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polygon(points: &[Vec2]) -> Polygon {
+        Polygon {
+            points: points.to_vec(),
+            position: Vec2::zeros(),
+            rotation: 0.,
+            scale: vec2(1., 1.),
+        }
+    }
+
+    fn square() -> Polygon {
+        polygon(&[vec2(0., 0.), vec2(2., 0.), vec2(2., 2.), vec2(0., 2.)])
+    }
+
+    fn assert_area_and_centroid(polygon: &Polygon, area: f32, centroid: Vec2) {
+        let (actual_area, actual_centroid) = polygon.area_and_centroid();
+
+        assert!((actual_area - area).abs() < 1e-5, "area {actual_area}");
+        assert!(
+            (actual_centroid - centroid).magnitude() < 1e-5,
+            "centroid {actual_centroid:?}"
+        );
+    }
+
+    #[test]
+    fn area_and_centroid_ignore_the_winding_order() {
+        let triangle = [vec2(0., 0.), vec2(3., 0.), vec2(0., 3.)];
+        let reversed: Vec<Vec2> = triangle.iter().rev().copied().collect();
+
+        assert_area_and_centroid(&square(), 4., vec2(1., 1.));
+        assert_area_and_centroid(&polygon(&triangle), 4.5, vec2(1., 1.));
+        assert_area_and_centroid(&polygon(&reversed), 4.5, vec2(1., 1.));
+    }
+
+    #[test]
+    fn degenerate_polygons_have_no_area() {
+        let line = polygon(&[vec2(0., 0.), vec2(1., 1.), vec2(2., 2.)]);
+
+        assert_eq!(line.area_and_centroid().0, 0.);
+        assert_eq!(polygon(&[]).area_and_centroid(), (0., Vec2::zeros()));
+    }
+
+    #[test]
+    fn clip_keeps_the_side_behind_the_plane() {
+        // The normal points towards negative y, so the half with y past 1 is kept
+        let clipped = square()
+            .clip(&Plane::new(vec2(0., 1.), vec2(2., 1.)))
+            .unwrap();
+
+        assert_area_and_centroid(&clipped, 2., vec2(1., 1.5));
+    }
+
+    #[test]
+    fn clip_past_the_polygon() {
+        let untouched = square()
+            .clip(&Plane::new(vec2(0., -1.), vec2(2., -1.)))
+            .unwrap();
+        assert_area_and_centroid(&untouched, 4., vec2(1., 1.));
+
+        assert!(square()
+            .clip(&Plane::new(vec2(0., 5.), vec2(2., 5.)))
+            .is_none());
+    }
+}
@@ -0,0 +1,191 @@
+use nalgebra_glm::Vec2;
+
+use crate::{arena::BodyHandle, collision_constraint::CollisionConstraint};
+
+/// Called for every touching pair after its manifold is generated and before the solver runs.
+/// Whatever it changes only lasts for the current step.
+pub type PreSolveHook = Box<dyn FnMut(&mut PreSolveContact) + Send>;
+
+/// A touching pair as seen by a pre-solve hook. Directions are given from body A to body B,
+/// whichever of them owns the reference face.
+pub struct PreSolveContact<'a> {
+    body_a: BodyHandle,
+    body_b: BodyHandle,
+    a_is_incident: bool,
+    constraint: &'a mut CollisionConstraint,
+}
+
+impl<'a> PreSolveContact<'a> {
+    pub fn new(
+        body_a: BodyHandle,
+        body_b: BodyHandle,
+        a_is_incident: bool,
+        constraint: &'a mut CollisionConstraint,
+    ) -> Self {
+        Self {
+            body_a,
+            body_b,
+            a_is_incident,
+            constraint,
+        }
+    }
+
+    pub fn body_a(&self) -> BodyHandle {
+        self.body_a
+    }
+
+    pub fn body_b(&self) -> BodyHandle {
+        self.body_b
+    }
+
+    /// Points from body A to body B.
+    pub fn normal(&self) -> Vec2 {
+        let normal = self
+            .constraint
+            .manifold()
+            .first()
+            .map_or(Vec2::default(), |contact| contact.normal());
+
+        // The manifold normal points from the reference body to the incident body
+        if self.a_is_incident {
+            -normal
+        } else {
+            normal
+        }
+    }
+
+    /// World space positions of the contact points.
+    pub fn points(&self) -> Vec<Vec2> {
+        self.constraint
+            .manifold()
+            .iter()
+            .map(|contact| contact.point())
+            .collect()
+    }
+
+    /// Lets the bodies pass through each other for this step.
+    pub fn disable(&mut self) {
+        self.constraint.set_enabled(false);
+    }
+
+    /// Ignores a single point of the manifold, in the order of `points`.
+    pub fn disable_point(&mut self, index: usize) {
+        if let Some(contact) = self.constraint.manifold_mut().get_mut(index) {
+            contact.set_enabled(false);
+        }
+    }
+
+    pub fn set_friction(&mut self, friction: f32) {
+        self.constraint.set_friction(friction);
+    }
+
+    pub fn set_restitution(&mut self, restitution: f32) {
+        self.constraint.set_restitution(restitution);
+    }
+
+    /// Makes body B slide over body A at `speed` along the tangent `(-normal.y, normal.x)`. This
+    /// is how conveyor belts move what lies on them.
+    pub fn set_tangent_speed(&mut self, speed: f32) {
+        // Flipping the normal also flips the tangent, so the speed means the same either way
+        self.constraint.set_tangent_speed(speed);
+    }
+}
+
+/// Contact properties of a single body, applied to every pair it is part of by a pre-solve hook.
+#[derive(Debug, Clone)]
+pub struct Surface {
+    pub body: BodyHandle,
+    /// Other bodies only collide with the surface from the side this direction points to
+    pub one_way: Option<Vec2>,
+    /// Speed of whatever lies on the surface along `(-normal.y, normal.x)`, where the normal
+    /// points out of the surface
+    pub conveyor_speed: Option<f32>,
+    pub friction: Option<f32>,
+    pub restitution: Option<f32>,
+}
+
+impl Surface {
+    pub fn apply(&self, contact: &mut PreSolveContact) {
+        let out_of_surface = if contact.body_a() == self.body {
+            contact.normal()
+        } else if contact.body_b() == self.body {
+            -contact.normal()
+        } else {
+            return;
+        };
+
+        if let Some(direction) = self.one_way {
+            if out_of_surface.dot(&direction.normalize()) < 0.5 {
+                contact.disable();
+                return;
+            }
+        }
+
+        // The normal and tangent both flip with the body order, so the speed does not have to
+        if let Some(speed) = self.conveyor_speed {
+            contact.set_tangent_speed(speed);
+        }
+
+        if let Some(friction) = self.friction {
+            contact.set_friction(friction);
+        }
+
+        if let Some(restitution) = self.restitution {
+            contact.set_restitution(restitution);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        arena::BodyArena,
+        body::Body,
+        constraints::Constraint,
+        contact_point::{ContactID, ContactPoint},
+        plane::Plane,
+    };
+
+    #[test]
+    fn disabled_points_get_no_impulse() {
+        // The incident body falls onto the reference body below it, touching at two corners
+        let incident = Body::test_square(Vec2::new(0., -1.)).into_shared();
+        let reference = Body::test_square(Vec2::zeros()).into_shared();
+        incident.write().unwrap().apply_impulse(Vec2::new(0., 5.));
+
+        let mut arena = BodyArena::default();
+        let (a, b) = (
+            arena.insert(incident.clone()),
+            arena.insert(reference.clone()),
+        );
+
+        let manifold = [-0.5, 0.5]
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| {
+                ContactPoint::new(
+                    Vec2::new(x, -0.5),
+                    Vec2::new(0., -1.),
+                    0.,
+                    Plane::default(),
+                    Plane::default(),
+                    ContactID::new(true, 0, false, i),
+                )
+            })
+            .collect();
+        let mut constraint = CollisionConstraint::new(manifold, incident, reference);
+
+        PreSolveContact::new(a, b, true, &mut constraint).disable_point(0);
+        constraint.pre_solve(60.);
+        constraint.solve();
+
+        let impulses: Vec<f32> = constraint
+            .manifold()
+            .iter()
+            .map(|contact| contact.accumulated_normal_impulse())
+            .collect();
+        assert_eq!(impulses[0], 0.);
+        assert!(impulses[1] > 0.);
+    }
+}
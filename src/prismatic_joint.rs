@@ -0,0 +1,295 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Axis, Constraint, Limits, Motor, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    gear_joint::JointCoordinate,
+    math::{rotate, solve_symmetric_2x2},
+};
+
+/// Lets two bodies slide along an axis fixed to the first body, without rotating relative to each
+/// other. The translation along the axis can be limited and driven by a motor.
+pub struct PrismaticJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    /// Unit axis in the rotated (but not scaled) frame of body A
+    local_axis: Vec2,
+    reference_angle: f32,
+
+    limits: Option<Limits>,
+    motor: Option<Motor>,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    axis: Axis,
+    perpendicular: Axis,
+    translation: f32,
+    position_error: Vec2,
+    axial_mass: f32,
+
+    // Accumulated over frames for warm starting. The first component is along the perpendicular,
+    // the second is the angular one.
+    impulse: Vec2,
+    motor_impulse: f32,
+    lower_impulse: f32,
+    upper_impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl PrismaticJoint {
+    /// `anchor` and `axis` are in world space, the axis does not have to be normalized but cannot
+    /// be zero. The bodies start at a translation of zero.
+    pub fn new(
+        body_a: SharedBody,
+        body_b: SharedBody,
+        anchor: Vec2,
+        axis: Vec2,
+        limits: Option<Limits>,
+        motor: Option<Motor>,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+        assert!(
+            axis.magnitude_squared() > 0.,
+            "Prismatic joints need a nonzero axis."
+        );
+
+        let (local_anchor_a, local_anchor_b, local_axis, reference_angle) = {
+            let a = body_a.read().expect("Body lock was poisoned.");
+            let b = body_b.read().expect("Body lock was poisoned.");
+
+            (
+                a.world_to_local(anchor),
+                b.world_to_local(anchor),
+                rotate(axis.normalize(), -a.rotation),
+                b.rotation - a.rotation,
+            )
+        };
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            local_axis,
+            reference_angle,
+            limits,
+            motor,
+            fixed_velocities: (None, None),
+            axis: Axis::default(),
+            perpendicular: Axis::default(),
+            translation: 0.,
+            position_error: Vec2::default(),
+            axial_mass: 0.,
+            impulse: Vec2::default(),
+            motor_impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// The translation along the axis, for coupling this joint to another with a gear joint.
+    pub fn coordinate(&self) -> JointCoordinate {
+        JointCoordinate::Prismatic {
+            body_a: self.body_a.clone(),
+            body_b: self.body_b.clone(),
+            local_anchor_a: self.local_anchor_a,
+            local_anchor_b: self.local_anchor_b,
+            local_axis: self.local_axis,
+        }
+    }
+
+    fn limit_bias(&self, error: f32) -> f32 {
+        if error > 0. {
+            error * self.inv_dt
+        } else {
+            BIAS_FACTOR * error * self.inv_dt
+        }
+    }
+}
+
+impl Constraint for PrismaticJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        let to_anchor_a = anchor_a - body_a.center_of_gravity();
+        let to_anchor_b = anchor_b - body_b.center_of_gravity();
+        let delta = anchor_b - anchor_a;
+
+        let axis = rotate(self.local_axis, body_a.rotation);
+        let perpendicular = Vec2::new(-axis.y, axis.x);
+        self.axis = Axis::new(axis, to_anchor_a, to_anchor_b, delta);
+        self.perpendicular = Axis::new(perpendicular, to_anchor_a, to_anchor_b, delta);
+
+        self.translation = axis.dot(&delta);
+        self.position_error = Vec2::new(
+            perpendicular.dot(&delta),
+            body_b.rotation - body_a.rotation - self.reference_angle,
+        );
+
+        self.axial_mass = self.axis.effective_mass(&body_a, &body_b);
+
+        if self.motor.is_none() {
+            self.motor_impulse = 0.;
+        }
+
+        if self.limits.is_none() {
+            self.lower_impulse = 0.;
+            self.upper_impulse = 0.;
+        }
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let axial_impulse = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        self.perpendicular
+            .apply(&mut body_a, &mut body_b, self.impulse.x);
+        self.axis.apply(&mut body_a, &mut body_b, axial_impulse);
+        body_a.apply_angular_impulse(-self.impulse.y);
+        body_b.apply_angular_impulse(self.impulse.y);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        if let Some(motor) = self.motor {
+            let speed = self.axis.speed(&body_a, &body_b);
+            let impulse = self.axial_mass * (motor.speed - speed);
+
+            let max_impulse = motor.max_force / self.inv_dt;
+            let accumulated = (self.motor_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = accumulated - self.motor_impulse;
+            self.motor_impulse = accumulated;
+
+            self.axis.apply(&mut body_a, &mut body_b, impulse);
+        }
+
+        if let Some(limits) = self.limits {
+            // Lower limit, the translation can only be pushed up
+            {
+                let bias = self.limit_bias(self.translation - limits.lower);
+                let speed = self.axis.speed(&body_a, &body_b);
+
+                let impulse = -self.axial_mass * (speed + bias);
+                let accumulated = (self.lower_impulse + impulse).max(0.);
+                let impulse = accumulated - self.lower_impulse;
+                self.lower_impulse = accumulated;
+
+                self.axis.apply(&mut body_a, &mut body_b, impulse);
+            }
+
+            // Upper limit, the translation can only be pushed down
+            {
+                let bias = self.limit_bias(limits.upper - self.translation);
+                let speed = -self.axis.speed(&body_a, &body_b);
+
+                let impulse = -self.axial_mass * (speed + bias);
+                let accumulated = (self.upper_impulse + impulse).max(0.);
+                let impulse = accumulated - self.upper_impulse;
+                self.upper_impulse = accumulated;
+
+                self.axis.apply(&mut body_a, &mut body_b, -impulse);
+            }
+        }
+
+        // Perpendicular and angular constraints, solved together
+        let (m_a, m_b) = (body_a.inv_mass(), body_b.inv_mass());
+        let (i_a, i_b) = (body_a.inv_inertia(), body_b.inv_inertia());
+        let (s_a, s_b) = (self.perpendicular.cross_a, self.perpendicular.cross_b);
+
+        let relative_velocity = Vec2::new(
+            self.perpendicular.speed(&body_a, &body_b),
+            body_b.angular_velocity() - body_a.angular_velocity(),
+        );
+        let bias = BIAS_FACTOR * self.inv_dt * self.position_error;
+
+        let k11 = m_a + m_b + i_a * s_a * s_a + i_b * s_b * s_b;
+        let k12 = i_a * s_a + i_b * s_b;
+        let k22 = if i_a + i_b == 0. { 1. } else { i_a + i_b };
+
+        let impulse = solve_symmetric_2x2(k11, k12, k22, -(relative_velocity + bias));
+        self.impulse += impulse;
+
+        self.perpendicular
+            .apply(&mut body_a, &mut body_b, impulse.x);
+        body_a.apply_angular_impulse(-impulse.y);
+        body_b.apply_angular_impulse(impulse.y);
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let (anchor_a, axis) = {
+            let body_a = self.body_a.read().expect("Body lock was poisoned.");
+            (
+                body_a.local_to_world(self.local_anchor_a),
+                rotate(self.local_axis, body_a.rotation),
+            )
+        };
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let color = color::get(ColorName::Teal);
+
+        // The rail is drawn over the allowed range, or a bit past the slider if there is none
+        let (lower, upper) = match self.limits {
+            Some(limits) => (limits.lower, limits.upper),
+            None => {
+                let translation = axis.dot(&(anchor_b - anchor_a));
+                (translation.min(0.) - 20., translation.max(0.) + 20.)
+            }
+        };
+
+        shapes.push(DebugShape::Line {
+            start: anchor_a + axis * lower,
+            end: anchor_a + axis * upper,
+            thickness: 2.,
+            color,
+        });
+        shapes.push(DebugShape::Circle {
+            center: anchor_b,
+            radius: 4.,
+            color,
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        let axial_impulse = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+
+        (self.impulse.x * self.perpendicular.direction + axial_impulse * self.axis.direction)
+            * inv_dt
+    }
+
+    fn reaction_torque(&self, inv_dt: f32) -> f32 {
+        self.impulse.y * inv_dt
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
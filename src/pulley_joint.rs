@@ -0,0 +1,202 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    math::cross,
+};
+
+/// Hangs two bodies from ropes that run over fixed points in the world. The rope on the side of
+/// body A plus `ratio` times the rope on the side of body B keeps the length it started with, so
+/// pulling one body down lifts the other.
+pub struct PulleyJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    ground_anchor_a: Vec2,
+    ground_anchor_b: Vec2,
+
+    ratio: f32,
+    total_length: f32,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    to_anchor_a: Vec2,
+    to_anchor_b: Vec2,
+    axis_a: Vec2,
+    axis_b: Vec2,
+    error: f32,
+    effective_mass: f32,
+
+    // Accumulated over frames for warm starting
+    impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl PulleyJoint {
+    /// The anchors are in the same local space as the collider points of their bodies, the ground
+    /// anchors are in world space.
+    pub fn new(
+        body_a: SharedBody,
+        local_anchor_a: Vec2,
+        ground_anchor_a: Vec2,
+        body_b: SharedBody,
+        local_anchor_b: Vec2,
+        ground_anchor_b: Vec2,
+        ratio: f32,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+
+        let total_length = {
+            let a = body_a.read().expect("Body lock was poisoned.");
+            let b = body_b.read().expect("Body lock was poisoned.");
+
+            (a.local_to_world(local_anchor_a) - ground_anchor_a).magnitude()
+                + ratio * (b.local_to_world(local_anchor_b) - ground_anchor_b).magnitude()
+        };
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            ground_anchor_a,
+            ground_anchor_b,
+            ratio,
+            total_length,
+            fixed_velocities: (None, None),
+            to_anchor_a: Vec2::default(),
+            to_anchor_b: Vec2::default(),
+            axis_a: Vec2::default(),
+            axis_b: Vec2::default(),
+            error: 0.,
+            effective_mass: 0.,
+            impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// Direction from the ground anchor to the body anchor, along with the length of that rope.
+    fn rope(ground_anchor: Vec2, anchor: Vec2) -> (Vec2, f32) {
+        let delta = anchor - ground_anchor;
+        let length = delta.magnitude();
+
+        if length > f32::EPSILON {
+            (delta / length, length)
+        } else {
+            (Vec2::default(), 0.)
+        }
+    }
+}
+
+impl Constraint for PulleyJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        self.to_anchor_a = anchor_a - body_a.center_of_gravity();
+        self.to_anchor_b = anchor_b - body_b.center_of_gravity();
+
+        let (axis_a, length_a) = Self::rope(self.ground_anchor_a, anchor_a);
+        let (axis_b, length_b) = Self::rope(self.ground_anchor_b, anchor_b);
+        self.axis_a = axis_a;
+        self.axis_b = axis_b;
+        self.error = self.total_length - length_a - self.ratio * length_b;
+
+        let cross_a = cross(self.to_anchor_a, self.axis_a);
+        let cross_b = cross(self.to_anchor_b, self.axis_b);
+        let inv_mass = body_a.inv_mass()
+            + body_a.inv_inertia() * cross_a * cross_a
+            + self.ratio
+                * self.ratio
+                * (body_b.inv_mass() + body_b.inv_inertia() * cross_b * cross_b);
+
+        self.effective_mass = if inv_mass > 0. { 1. / inv_mass } else { 0. };
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        body_a.apply_impulse_at(-self.impulse * self.axis_a, self.to_anchor_a);
+        body_b.apply_impulse_at(-self.ratio * self.impulse * self.axis_b, self.to_anchor_b);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        // Rate at which the total rope length shrinks
+        let speed = -self.axis_a.dot(&body_a.velocity_at(self.to_anchor_a))
+            - self.ratio * self.axis_b.dot(&body_b.velocity_at(self.to_anchor_b));
+        let bias = BIAS_FACTOR * self.inv_dt * self.error;
+
+        let impulse = -self.effective_mass * (speed + bias);
+        self.impulse += impulse;
+
+        body_a.apply_impulse_at(-impulse * self.axis_a, self.to_anchor_a);
+        body_b.apply_impulse_at(-self.ratio * impulse * self.axis_b, self.to_anchor_b);
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor_a = self
+            .body_a
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_a);
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let color = color::get(ColorName::Yellow);
+
+        shapes.push(DebugShape::LineStrip {
+            points: vec![
+                anchor_a,
+                self.ground_anchor_a,
+                self.ground_anchor_b,
+                anchor_b,
+            ],
+            color,
+        });
+        for center in [self.ground_anchor_a, self.ground_anchor_b] {
+            shapes.push(DebugShape::CircleLines {
+                center,
+                radius: 6.,
+                color,
+            });
+        }
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        -self.ratio * self.impulse * inv_dt * self.axis_b
+    }
+
+    fn reaction_torque(&self, _inv_dt: f32) -> f32 {
+        0.
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
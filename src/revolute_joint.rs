@@ -0,0 +1,270 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, Limits, Motor, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    gear_joint::JointCoordinate,
+    math::solve_symmetric_2x2,
+};
+
+/// Pins two bodies together at a shared anchor, leaving only their relative rotation free. The
+/// relative angle can be limited and driven by a motor.
+pub struct RevoluteJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    reference_angle: f32,
+
+    limits: Option<Limits>,
+    motor: Option<Motor>,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    to_anchor_a: Vec2,
+    to_anchor_b: Vec2,
+    position_error: Vec2,
+    angle: f32,
+    axial_mass: f32,
+
+    // Accumulated over frames for warm starting
+    linear_impulse: Vec2,
+    motor_impulse: f32,
+    lower_impulse: f32,
+    upper_impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl RevoluteJoint {
+    /// Both bodies are pinned at `anchor`, given in world space. The current relative angle of
+    /// the bodies is taken as the zero angle of the joint.
+    pub fn new(
+        body_a: SharedBody,
+        body_b: SharedBody,
+        anchor: Vec2,
+        limits: Option<Limits>,
+        motor: Option<Motor>,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+
+        let (local_anchor_a, local_anchor_b, reference_angle) = {
+            let a = body_a.read().expect("Body lock was poisoned.");
+            let b = body_b.read().expect("Body lock was poisoned.");
+
+            (
+                a.world_to_local(anchor),
+                b.world_to_local(anchor),
+                b.rotation - a.rotation,
+            )
+        };
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            reference_angle,
+            limits,
+            motor,
+            fixed_velocities: (None, None),
+            to_anchor_a: Vec2::default(),
+            to_anchor_b: Vec2::default(),
+            position_error: Vec2::default(),
+            angle: 0.,
+            axial_mass: 0.,
+            linear_impulse: Vec2::default(),
+            motor_impulse: 0.,
+            lower_impulse: 0.,
+            upper_impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// The relative angle of the bodies, for coupling this joint to another with a gear joint.
+    pub fn coordinate(&self) -> JointCoordinate {
+        JointCoordinate::Revolute {
+            body_a: self.body_a.clone(),
+            body_b: self.body_b.clone(),
+            reference_angle: self.reference_angle,
+        }
+    }
+
+    fn apply_angular(body_a: &mut SolverBody, body_b: &mut SolverBody, impulse: f32) {
+        body_a.apply_angular_impulse(-impulse);
+        body_b.apply_angular_impulse(impulse);
+    }
+}
+
+impl Constraint for RevoluteJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        self.to_anchor_a = anchor_a - body_a.center_of_gravity();
+        self.to_anchor_b = anchor_b - body_b.center_of_gravity();
+        self.position_error = anchor_b - anchor_a;
+        self.angle = body_b.rotation - body_a.rotation - self.reference_angle;
+
+        let inv_inertia = body_a.inv_inertia() + body_b.inv_inertia();
+        self.axial_mass = if inv_inertia > 0. {
+            1. / inv_inertia
+        } else {
+            0.
+        };
+
+        if self.motor.is_none() {
+            self.motor_impulse = 0.;
+        }
+
+        if self.limits.is_none() {
+            self.lower_impulse = 0.;
+            self.upper_impulse = 0.;
+        }
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let axial_impulse = self.motor_impulse + self.lower_impulse - self.upper_impulse;
+
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        body_a.apply_impulse_at(-self.linear_impulse, self.to_anchor_a);
+        body_b.apply_impulse_at(self.linear_impulse, self.to_anchor_b);
+        Self::apply_angular(&mut body_a, &mut body_b, axial_impulse);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        if let Some(motor) = self.motor {
+            let relative_speed = body_b.angular_velocity() - body_a.angular_velocity();
+            let impulse = -self.axial_mass * (relative_speed - motor.speed);
+
+            let max_impulse = motor.max_force / self.inv_dt;
+            let accumulated = (self.motor_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = accumulated - self.motor_impulse;
+            self.motor_impulse = accumulated;
+
+            Self::apply_angular(&mut body_a, &mut body_b, impulse);
+        }
+
+        if let Some(limits) = self.limits {
+            // Lower limit, the angle can only be pushed up
+            {
+                let error = self.angle - limits.lower;
+                let bias = if error > 0. {
+                    error * self.inv_dt
+                } else {
+                    BIAS_FACTOR * error * self.inv_dt
+                };
+
+                let relative_speed = body_b.angular_velocity() - body_a.angular_velocity();
+                let impulse = -self.axial_mass * (relative_speed + bias);
+                let accumulated = (self.lower_impulse + impulse).max(0.);
+                let impulse = accumulated - self.lower_impulse;
+                self.lower_impulse = accumulated;
+
+                Self::apply_angular(&mut body_a, &mut body_b, impulse);
+            }
+
+            // Upper limit, the angle can only be pushed down
+            {
+                let error = limits.upper - self.angle;
+                let bias = if error > 0. {
+                    error * self.inv_dt
+                } else {
+                    BIAS_FACTOR * error * self.inv_dt
+                };
+
+                let relative_speed = body_a.angular_velocity() - body_b.angular_velocity();
+                let impulse = -self.axial_mass * (relative_speed + bias);
+                let accumulated = (self.upper_impulse + impulse).max(0.);
+                let impulse = accumulated - self.upper_impulse;
+                self.upper_impulse = accumulated;
+
+                Self::apply_angular(&mut body_a, &mut body_b, -impulse);
+            }
+        }
+
+        // Point to point
+        let relative_velocity =
+            body_b.velocity_at(self.to_anchor_b) - body_a.velocity_at(self.to_anchor_a);
+        let bias = BIAS_FACTOR * self.inv_dt * self.position_error;
+
+        let (r_a, r_b) = (self.to_anchor_a, self.to_anchor_b);
+        let (m_a, m_b) = (body_a.inv_mass(), body_b.inv_mass());
+        let (i_a, i_b) = (body_a.inv_inertia(), body_b.inv_inertia());
+
+        let k11 = m_a + m_b + r_a.y * r_a.y * i_a + r_b.y * r_b.y * i_b;
+        let k12 = -r_a.y * r_a.x * i_a - r_b.y * r_b.x * i_b;
+        let k22 = m_a + m_b + r_a.x * r_a.x * i_a + r_b.x * r_b.x * i_b;
+
+        let impulse = solve_symmetric_2x2(k11, k12, k22, -(relative_velocity + bias));
+        self.linear_impulse += impulse;
+
+        body_a.apply_impulse_at(-impulse, r_a);
+        body_b.apply_impulse_at(impulse, r_b);
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let (center_a, anchor) = {
+            let body_a = self.body_a.read().expect("Body lock was poisoned.");
+            (
+                body_a.center_of_gravity(),
+                body_a.local_to_world(self.local_anchor_a),
+            )
+        };
+        let center_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .center_of_gravity();
+
+        let color = color::get(ColorName::Mauve);
+
+        for center in [center_a, center_b] {
+            shapes.push(DebugShape::Line {
+                start: center,
+                end: anchor,
+                thickness: 2.,
+                color,
+            });
+        }
+        shapes.push(DebugShape::CircleLines {
+            center: anchor,
+            radius: 6.,
+            color,
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        self.linear_impulse * inv_dt
+    }
+
+    fn reaction_torque(&self, inv_dt: f32) -> f32 {
+        (self.motor_impulse + self.lower_impulse - self.upper_impulse) * inv_dt
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
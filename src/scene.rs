@@ -0,0 +1,840 @@
+use std::{fmt, fs};
+
+use nalgebra_glm::Vec2;
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    arena::BodyHandle,
+    body::{Body, SharedBody},
+    constraints::{BreakLimits, Constraint, Limits, Motor},
+    distance_joint::DistanceJoint,
+    fluid::FluidVolume,
+    force_generator::{AirDrag, AnchorSpring, Explosion, ForceGenerator, Wind},
+    gravity::{self, MutualGravity, PointAttractor},
+    pre_solve::{PreSolveContact, Surface},
+    prismatic_joint::PrismaticJoint,
+    pulley_joint::PulleyJoint,
+    revolute_joint::RevoluteJoint,
+    spring_joint::{SpringJoint, Stiffness},
+    vehicle::{Vehicle, Wheel},
+    weld_joint::WeldJoint,
+    wheel_joint::WheelJoint,
+    world::{JointHandle, World},
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Scene {
+    bodies: Vec<Body>,
+    /// Uniform gravity of the world, there is none if it is left out
+    #[serde(default)]
+    gravity: Option<Vec2>,
+    #[serde(default)]
+    joints: Vec<JointEntry>,
+    #[serde(default)]
+    chains: Vec<ChainDefinition>,
+    #[serde(default)]
+    vehicles: Vec<VehicleDefinition>,
+    #[serde(default)]
+    surfaces: Vec<SurfaceDefinition>,
+    #[serde(default)]
+    forces: Vec<ForceDefinition>,
+}
+
+/// What a scene added to the world, in the order it appears in the scene file.
+pub struct SceneHandles {
+    pub bodies: Vec<BodyHandle>,
+    pub vehicles: Vec<Vehicle>,
+}
+
+/// Refers to a body of the scene either by its position in the body list or by its name. In the
+/// scene file it is a number or a string.
+#[derive(Debug, Clone)]
+pub enum BodyReference {
+    Index(usize),
+    Name(String),
+}
+
+impl Serialize for BodyReference {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BodyReference::Index(index) => serializer.serialize_u64(*index as u64),
+            BodyReference::Name(name) => serializer.serialize_str(name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for BodyReference {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReferenceVisitor;
+
+        impl Visitor<'_> for ReferenceVisitor {
+            type Value = BodyReference;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a body index or name")
+            }
+
+            fn visit_u64<E: de::Error>(self, index: u64) -> Result<Self::Value, E> {
+                usize::try_from(index)
+                    .map(BodyReference::Index)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(index), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Self::Value, E> {
+                Ok(BodyReference::Name(name.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(ReferenceVisitor)
+    }
+}
+
+/// A joint of the scene. It breaks once its reaction force or torque goes past the break limits,
+/// if there are any. With `collide_connected` turned off the bodies it connects pass through each
+/// other.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct JointEntry {
+    #[serde(flatten)]
+    joint: JointDefinition,
+    #[serde(default)]
+    break_force: Option<f32>,
+    #[serde(default)]
+    break_torque: Option<f32>,
+    #[serde(default = "default_collide_connected")]
+    collide_connected: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum JointDefinition {
+    Distance(DistanceJointDefinition),
+    Revolute(RevoluteJointDefinition),
+    Prismatic(PrismaticJointDefinition),
+    Weld(WeldJointDefinition),
+    Spring(SpringJointDefinition),
+    Rope(RopeJointDefinition),
+    Pulley(PulleyJointDefinition),
+    Gear(GearJointDefinition),
+    Wheel(WheelJointDefinition),
+}
+
+/// If no lengths are given the joint is rigid at the distance the anchors start at. Giving only
+/// one of the limits leaves the other one at the starting distance.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DistanceJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    local_anchor_a: Vec2,
+    #[serde(default)]
+    local_anchor_b: Vec2,
+    #[serde(default)]
+    length: Option<f32>,
+    #[serde(default)]
+    min_length: Option<f32>,
+    #[serde(default)]
+    max_length: Option<f32>,
+}
+
+/// The anchor is in world space, at the position the bodies start at.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevoluteJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    anchor: Vec2,
+    #[serde(default)]
+    limits: Option<Limits>,
+    #[serde(default)]
+    motor: Option<Motor>,
+}
+
+/// The anchor and axis are in world space. The axis moves along with body A.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrismaticJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    anchor: Vec2,
+    axis: Vec2,
+    #[serde(default)]
+    limits: Option<Limits>,
+    #[serde(default)]
+    motor: Option<Motor>,
+}
+
+/// A frequency of zero (the default) makes the weld rigid.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WeldJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    anchor: Vec2,
+    #[serde(default)]
+    frequency: f32,
+    #[serde(default = "default_damping_ratio")]
+    damping_ratio: f32,
+}
+
+/// Either `frequency` (in hertz) or `stiffness` (spring constant) has to be given. The rest length
+/// defaults to the distance the anchors start at.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SpringJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    local_anchor_a: Vec2,
+    #[serde(default)]
+    local_anchor_b: Vec2,
+    #[serde(default)]
+    rest_length: Option<f32>,
+    #[serde(default)]
+    frequency: Option<f32>,
+    #[serde(default)]
+    stiffness: Option<f32>,
+    #[serde(default = "default_damping_ratio")]
+    damping_ratio: f32,
+}
+
+/// The maximum length defaults to the distance the anchors start at.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RopeJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    local_anchor_a: Vec2,
+    #[serde(default)]
+    local_anchor_b: Vec2,
+    #[serde(default)]
+    max_length: Option<f32>,
+}
+
+/// The ground anchors are in world space.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PulleyJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    local_anchor_a: Vec2,
+    #[serde(default)]
+    local_anchor_b: Vec2,
+    ground_anchor_a: Vec2,
+    ground_anchor_b: Vec2,
+    #[serde(default = "default_ratio")]
+    ratio: f32,
+}
+
+/// Couples two revolute or prismatic joints, given by their position in the joint list. Those
+/// joints have to come before the gear.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GearJointDefinition {
+    joint_a: usize,
+    joint_b: usize,
+    #[serde(default = "default_ratio")]
+    ratio: f32,
+}
+
+/// The anchor defaults to the center of the wheel (body B) and the axis to straight down, both
+/// in world space. A frequency of zero makes the suspension rigid.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WheelJointDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    anchor: Option<Vec2>,
+    #[serde(default = "default_suspension_axis")]
+    axis: Vec2,
+    #[serde(default = "default_suspension_frequency")]
+    frequency: f32,
+    #[serde(default = "default_damping_ratio")]
+    damping_ratio: f32,
+    #[serde(default)]
+    motor: Option<Motor>,
+}
+
+/// A chassis with wheels attached by wheel joints, which can be driven with a throttle and brake.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VehicleDefinition {
+    chassis: BodyReference,
+    wheels: Vec<WheelDefinition>,
+    max_speed: f32,
+    max_torque: f32,
+    brake_torque: f32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WheelDefinition {
+    body: BodyReference,
+    #[serde(default = "default_suspension_axis")]
+    axis: Vec2,
+    #[serde(default = "default_suspension_frequency")]
+    frequency: f32,
+    #[serde(default = "default_damping_ratio")]
+    damping_ratio: f32,
+    #[serde(default = "default_driven")]
+    driven: bool,
+}
+
+/// Changes how the contacts of a body are solved: one way platforms (`one_way` is the direction
+/// bodies can land from), conveyor belts and surfaces with their own friction or restitution.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SurfaceDefinition {
+    body: BodyReference,
+    #[serde(default)]
+    one_way: Option<Vec2>,
+    #[serde(default)]
+    conveyor_speed: Option<f32>,
+    #[serde(default)]
+    friction: Option<f32>,
+    #[serde(default)]
+    restitution: Option<f32>,
+}
+
+/// A force generator of the scene. An explosion goes off as soon as the scene starts.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ForceDefinition {
+    Wind(Wind),
+    AirDrag(AirDrag),
+    Explosion(Explosion),
+    Fluid(FluidDefinition),
+    Attractor(PointAttractor),
+    MutualGravity(MutualGravityDefinition),
+    AnchorSpring(AnchorSpringDefinition),
+}
+
+/// The anchor is in world space.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AnchorSpringDefinition {
+    body: BodyReference,
+    anchor: Vec2,
+    stiffness: f32,
+}
+
+/// Attraction between the listed bodies. With a radius, bodies further apart than it do not pull
+/// on each other.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MutualGravityDefinition {
+    bodies: Vec<BodyReference>,
+    strength: f32,
+    #[serde(default)]
+    radius: Option<f32>,
+    /// Distance below which the pull between two bodies stops growing
+    #[serde(default = "gravity::default_softening")]
+    softening: f32,
+}
+
+/// Water hanging `depth` below the surface between the two points, with the surface going from
+/// left to right.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FluidDefinition {
+    surface_start: Vec2,
+    surface_end: Vec2,
+    depth: f32,
+    density: f32,
+    #[serde(default)]
+    linear_drag: f32,
+    #[serde(default)]
+    angular_drag: f32,
+}
+
+/// A chain of `link_count` links laid out in a straight line between the anchors of two bodies.
+/// Neighbouring links are pinned together with revolute joints and do not collide with each other.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ChainDefinition {
+    body_a: BodyReference,
+    body_b: BodyReference,
+    #[serde(default)]
+    local_anchor_a: Vec2,
+    #[serde(default)]
+    local_anchor_b: Vec2,
+    link_count: usize,
+    #[serde(default = "default_link_width")]
+    link_width: f32,
+    #[serde(default = "default_link_mass")]
+    link_mass: f32,
+    #[serde(default = "default_link_collider")]
+    collider_file: String,
+}
+
+fn default_link_width() -> f32 {
+    10.
+}
+
+fn default_link_mass() -> f32 {
+    1.
+}
+
+fn default_link_collider() -> String {
+    "StdSquare.json".to_string()
+}
+
+fn default_suspension_axis() -> Vec2 {
+    Vec2::new(0., 1.)
+}
+
+fn default_suspension_frequency() -> f32 {
+    4.
+}
+
+fn default_driven() -> bool {
+    true
+}
+
+fn default_collide_connected() -> bool {
+    true
+}
+
+fn default_ratio() -> f32 {
+    1.
+}
+
+fn default_damping_ratio() -> f32 {
+    0.7
+}
+
+impl Scene {
+    pub fn load(path: &str) -> Self {
+        let scene_file = fs::read_to_string(path).expect("Could not find the setup file.");
+
+        // Setup files used to only be a list of bodies, which is still accepted. Picking the format
+        // up front means a mistake is reported against the format the file is actually in.
+        if !scene_file.trim_start().starts_with('[') {
+            return serde_json::from_str(&scene_file).expect("Unable to read the setup file");
+        }
+
+        let bodies = serde_json::from_str(&scene_file).expect("Unable to read the setup file");
+        Scene {
+            bodies,
+            gravity: None,
+            joints: vec![],
+            chains: vec![],
+            vehicles: vec![],
+            surfaces: vec![],
+            forces: vec![],
+        }
+    }
+
+    /// Adds the contents of the scene to the world.
+    pub fn build(self, world: &mut World) -> SceneHandles {
+        if let Some(gravity) = self.gravity {
+            world.set_gravity(gravity);
+        }
+
+        let names: Vec<Option<String>> = self
+            .bodies
+            .iter()
+            .map(|body| body.name().map(str::to_string))
+            .collect();
+
+        let handles: Vec<BodyHandle> = self
+            .bodies
+            .into_iter()
+            .map(|mut body| {
+                body.construct_collider();
+                world.add_body(body)
+            })
+            .collect();
+
+        let resolve = |reference: &BodyReference| -> BodyHandle {
+            let index = match reference {
+                BodyReference::Index(index) => *index,
+                BodyReference::Name(name) => names
+                    .iter()
+                    .position(|other| other.as_deref() == Some(name.as_str()))
+                    .unwrap_or_else(|| panic!("There is no body named \"{name}\".")),
+            };
+
+            *handles
+                .get(index)
+                .unwrap_or_else(|| panic!("There is no body at index {index}."))
+        };
+
+        let mut joints: Vec<JointHandle> = vec![];
+        for entry in self.joints.iter() {
+            let joint = entry.joint.build(world, &resolve, &joints);
+
+            if entry.break_force.is_some() || entry.break_torque.is_some() {
+                let limits = BreakLimits {
+                    force: entry.break_force.unwrap_or(f32::INFINITY),
+                    torque: entry.break_torque.unwrap_or(f32::INFINITY),
+                };
+
+                world.set_break_limits(joint, limits);
+            }
+
+            if !entry.collide_connected {
+                world.set_collide_connected(joint, false);
+            }
+
+            joints.push(joint);
+        }
+
+        for chain in self.chains.iter() {
+            chain.build(world, &resolve);
+        }
+
+        let surfaces: Vec<Surface> = self
+            .surfaces
+            .iter()
+            .map(|surface| Surface {
+                body: resolve(&surface.body),
+                one_way: surface.one_way,
+                conveyor_speed: surface.conveyor_speed,
+                friction: surface.friction,
+                restitution: surface.restitution,
+            })
+            .collect();
+
+        if !surfaces.is_empty() {
+            world.set_pre_solve_hook(Box::new(move |contact: &mut PreSolveContact| {
+                for surface in surfaces.iter() {
+                    surface.apply(contact);
+                }
+            }));
+        }
+
+        for force in self.forces.iter() {
+            let generator: Box<dyn ForceGenerator> = match force {
+                ForceDefinition::Wind(wind) => Box::new(*wind),
+                ForceDefinition::AirDrag(drag) => Box::new(*drag),
+                ForceDefinition::Explosion(explosion) => Box::new(*explosion),
+                ForceDefinition::Fluid(fluid) => Box::new(FluidVolume::new(
+                    fluid.surface_start,
+                    fluid.surface_end,
+                    fluid.depth,
+                    fluid.density,
+                    fluid.linear_drag,
+                    fluid.angular_drag,
+                )),
+                ForceDefinition::Attractor(attractor) => Box::new(*attractor),
+                ForceDefinition::MutualGravity(definition) => Box::new(MutualGravity::new(
+                    definition.bodies.iter().map(&resolve).collect(),
+                    definition.strength,
+                    definition.radius,
+                    definition.softening,
+                )),
+                ForceDefinition::AnchorSpring(definition) => Box::new(AnchorSpring::new(
+                    resolve(&definition.body),
+                    definition.anchor,
+                    definition.stiffness,
+                )),
+            };
+
+            world.add_force_generator(generator);
+        }
+
+        let vehicles = self
+            .vehicles
+            .iter()
+            .map(|vehicle| vehicle.build(world, &resolve))
+            .collect();
+
+        SceneHandles {
+            bodies: handles,
+            vehicles,
+        }
+    }
+}
+
+impl JointDefinition {
+    /// Adds the joint to the world. `previous` are the joints of the scene added before it.
+    fn build(
+        &self,
+        world: &mut World,
+        resolve: &impl Fn(&BodyReference) -> BodyHandle,
+        previous: &[JointHandle],
+    ) -> JointHandle {
+        let body = |reference: &BodyReference| -> SharedBody {
+            world
+                .shared_body(resolve(reference))
+                .expect("Bodies of the scene were just added.")
+        };
+
+        let constraint: Box<dyn Constraint> = match self {
+            JointDefinition::Distance(definition) => {
+                let body_a = body(&definition.body_a);
+                let body_b = body(&definition.body_b);
+
+                let start_length = anchor_distance(
+                    &body_a,
+                    definition.local_anchor_a,
+                    &body_b,
+                    definition.local_anchor_b,
+                );
+
+                let length = definition.length.unwrap_or(start_length);
+
+                Box::new(DistanceJoint::new(
+                    body_a,
+                    definition.local_anchor_a,
+                    body_b,
+                    definition.local_anchor_b,
+                    definition.min_length.unwrap_or(length),
+                    definition.max_length.unwrap_or(length),
+                ))
+            }
+            JointDefinition::Revolute(definition) => Box::new(RevoluteJoint::new(
+                body(&definition.body_a),
+                body(&definition.body_b),
+                definition.anchor,
+                definition.limits,
+                definition.motor,
+            )),
+            JointDefinition::Prismatic(definition) => Box::new(PrismaticJoint::new(
+                body(&definition.body_a),
+                body(&definition.body_b),
+                definition.anchor,
+                definition.axis,
+                definition.limits,
+                definition.motor,
+            )),
+            JointDefinition::Weld(definition) => Box::new(WeldJoint::new(
+                body(&definition.body_a),
+                body(&definition.body_b),
+                definition.anchor,
+                definition.frequency,
+                definition.damping_ratio,
+            )),
+            JointDefinition::Spring(definition) => {
+                let body_a = body(&definition.body_a);
+                let body_b = body(&definition.body_b);
+
+                let rest_length = definition.rest_length.unwrap_or_else(|| {
+                    anchor_distance(
+                        &body_a,
+                        definition.local_anchor_a,
+                        &body_b,
+                        definition.local_anchor_b,
+                    )
+                });
+
+                let stiffness = match (definition.stiffness, definition.frequency) {
+                    (Some(constant), _) => Stiffness::Constant(constant),
+                    (None, Some(frequency)) => Stiffness::Frequency(frequency),
+                    (None, None) => panic!("Springs need either a frequency or a stiffness."),
+                };
+
+                Box::new(SpringJoint::new(
+                    body_a,
+                    definition.local_anchor_a,
+                    body_b,
+                    definition.local_anchor_b,
+                    rest_length,
+                    stiffness,
+                    definition.damping_ratio,
+                ))
+            }
+            JointDefinition::Rope(definition) => {
+                let body_a = body(&definition.body_a);
+                let body_b = body(&definition.body_b);
+
+                let max_length = definition.max_length.unwrap_or_else(|| {
+                    anchor_distance(
+                        &body_a,
+                        definition.local_anchor_a,
+                        &body_b,
+                        definition.local_anchor_b,
+                    )
+                });
+
+                Box::new(DistanceJoint::rope(
+                    body_a,
+                    definition.local_anchor_a,
+                    body_b,
+                    definition.local_anchor_b,
+                    max_length,
+                ))
+            }
+            JointDefinition::Pulley(definition) => Box::new(PulleyJoint::new(
+                body(&definition.body_a),
+                definition.local_anchor_a,
+                definition.ground_anchor_a,
+                body(&definition.body_b),
+                definition.local_anchor_b,
+                definition.ground_anchor_b,
+                definition.ratio,
+            )),
+            JointDefinition::Gear(definition) => {
+                let joint = |index: usize| {
+                    *previous.get(index).unwrap_or_else(|| {
+                        panic!("Gear joints can only refer to joints before them, not {index}.")
+                    })
+                };
+
+                return world
+                    .add_gear(
+                        joint(definition.joint_a),
+                        joint(definition.joint_b),
+                        definition.ratio,
+                    )
+                    .expect("Gear joints can only couple revolute and prismatic joints.");
+            }
+            JointDefinition::Wheel(definition) => {
+                let body_a = body(&definition.body_a);
+                let body_b = body(&definition.body_b);
+
+                let anchor = definition.anchor.unwrap_or_else(|| {
+                    body_b
+                        .read()
+                        .expect("Body lock was poisoned.")
+                        .center_of_gravity()
+                });
+
+                Box::new(WheelJoint::new(
+                    body_a,
+                    body_b,
+                    anchor,
+                    definition.axis,
+                    definition.frequency,
+                    definition.damping_ratio,
+                    definition.motor,
+                ))
+            }
+        };
+
+        world.add_constraint(constraint)
+    }
+}
+
+impl VehicleDefinition {
+    fn build(&self, world: &mut World, resolve: &impl Fn(&BodyReference) -> BodyHandle) -> Vehicle {
+        let chassis_handle = resolve(&self.chassis);
+        let chassis = world
+            .shared_body(chassis_handle)
+            .expect("Bodies of the scene were just added.");
+
+        let wheels = self
+            .wheels
+            .iter()
+            .map(|wheel| {
+                let wheel_handle = resolve(&wheel.body);
+                let body = world
+                    .shared_body(wheel_handle)
+                    .expect("Bodies of the scene were just added.");
+                let anchor = body
+                    .read()
+                    .expect("Body lock was poisoned.")
+                    .center_of_gravity();
+
+                let joint = WheelJoint::new(
+                    chassis.clone(),
+                    body,
+                    anchor,
+                    wheel.axis,
+                    wheel.frequency,
+                    wheel.damping_ratio,
+                    None,
+                );
+
+                world.disable_collision(chassis_handle, wheel_handle);
+
+                Wheel {
+                    joint: world.add_constraint(Box::new(joint)),
+                    driven: wheel.driven,
+                }
+            })
+            .collect();
+
+        Vehicle::new(wheels, self.max_speed, self.max_torque, self.brake_torque)
+    }
+}
+
+impl ChainDefinition {
+    fn build(&self, world: &mut World, resolve: &impl Fn(&BodyReference) -> BodyHandle) {
+        let handle_a = resolve(&self.body_a);
+        let handle_b = resolve(&self.body_b);
+
+        let start = world
+            .get(handle_a)
+            .expect("Bodies of the scene were just added.")
+            .local_to_world(self.local_anchor_a);
+        let end = world
+            .get(handle_b)
+            .expect("Bodies of the scene were just added.")
+            .local_to_world(self.local_anchor_b);
+
+        let link_count = self.link_count.max(1);
+        let step = (end - start) / link_count as f32;
+        let rotation = step.y.atan2(step.x);
+        let scale = Vec2::new(step.magnitude() / 2., self.link_width / 2.);
+
+        // Inertia of a rectangle spanning twice the scale
+        let inertia = self.link_mass * (scale.x * scale.x + scale.y * scale.y) / 3.;
+
+        let mut previous = handle_a;
+
+        for i in 0..link_count {
+            let mut link = Body::new(
+                &self.collider_file,
+                start + step * (i as f32 + 0.5),
+                rotation,
+                scale,
+                self.link_mass,
+                inertia,
+            );
+            link.construct_collider();
+
+            let link = world.add_body(link);
+            Self::pin(world, previous, link, start + step * i as f32);
+            previous = link;
+        }
+
+        Self::pin(world, previous, handle_b, end);
+    }
+
+    fn pin(world: &mut World, a: BodyHandle, b: BodyHandle, anchor: Vec2) {
+        let joint = RevoluteJoint::new(
+            world.shared_body(a).expect("Added by the chain."),
+            world.shared_body(b).expect("Added by the chain."),
+            anchor,
+            None,
+            None,
+        );
+
+        world.add_constraint(Box::new(joint));
+        world.disable_collision(a, b);
+    }
+}
+
+/// Distance between two anchor points, given in the local space of their bodies.
+fn anchor_distance(
+    body_a: &SharedBody,
+    local_anchor_a: Vec2,
+    body_b: &SharedBody,
+    local_anchor_b: Vec2,
+) -> f32 {
+    let body_a = body_a.read().expect("Body lock was poisoned.");
+    let body_b = body_b.read().expect("Body lock was poisoned.");
+
+    (body_b.local_to_world(local_anchor_b) - body_a.local_to_world(local_anchor_a)).magnitude()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_references_are_numbers_or_strings() {
+        let index: BodyReference = serde_json::from_str("3").unwrap();
+        let name: BodyReference = serde_json::from_str("\"wheel\"").unwrap();
+
+        assert!(matches!(index, BodyReference::Index(3)));
+        assert!(matches!(name, BodyReference::Name(name) if name == "wheel"));
+    }
+
+    #[test]
+    fn invalid_body_references_say_what_was_expected() {
+        let error = serde_json::from_str::<BodyReference>("1.5").unwrap_err();
+
+        assert!(error.to_string().contains("a body index or name"));
+    }
+
+    #[test]
+    fn joints_report_their_own_errors() {
+        let error = serde_json::from_str::<JointEntry>(r#"{ "type": "Distance", "body_a": 0 }"#)
+            .unwrap_err();
+
+        assert!(error.to_string().contains("body_b"));
+    }
+}
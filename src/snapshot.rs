@@ -0,0 +1,38 @@
+use catppuccin::ColorName;
+use nalgebra_glm::Mat3x3;
+use raylib::prelude::*;
+
+use crate::{color, debug_draw::DebugShape, polygon::Polygon};
+
+/// Immutable copy of what the viewer needs to draw a single step of the world.
+pub struct WorldSnapshot {
+    pub bodies: Vec<BodySnapshot>,
+    /// Force generators, joints and contacts, drawn on top of the bodies
+    pub shapes: Vec<DebugShape>,
+    pub energy: f32,
+}
+
+pub struct BodySnapshot {
+    pub transform: Mat3x3,
+    pub collider: Option<Polygon>,
+}
+
+impl WorldSnapshot {
+    pub fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>) {
+        for body in self.bodies.iter() {
+            body.draw(handle);
+        }
+
+        for shape in self.shapes.iter() {
+            shape.draw(handle);
+        }
+    }
+}
+
+impl BodySnapshot {
+    fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>) {
+        if let Some(collider) = &self.collider {
+            collider.draw(&self.transform, handle, color::get(ColorName::Flamingo));
+        }
+    }
+}
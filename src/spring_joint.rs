@@ -0,0 +1,261 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, Softness},
+    debug_draw::DebugShape,
+    math::cross,
+};
+
+/// How stiff a spring is, either directly or as the frequency it oscillates at.
+#[derive(Debug, Clone, Copy)]
+pub enum Stiffness {
+    /// Natural frequency in hertz
+    Frequency(f32),
+    /// Spring constant, force per unit of stretch
+    Constant(f32),
+}
+
+/// Damped spring between the anchor points of two bodies. It is solved as a soft constraint
+/// rather than applied as a force, so it stays stable at high stiffness.
+pub struct SpringJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+
+    rest_length: f32,
+    stiffness: Stiffness,
+    damping_ratio: f32,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    to_anchor_a: Vec2,
+    to_anchor_b: Vec2,
+    axis: Vec2,
+    stretch: f32,
+    effective_mass: f32,
+    softness: Softness,
+
+    // Accumulated over frames for warm starting
+    impulse: f32,
+}
+
+impl SpringJoint {
+    /// The anchors are in the same local space as the collider points of their bodies.
+    pub fn new(
+        body_a: SharedBody,
+        local_anchor_a: Vec2,
+        body_b: SharedBody,
+        local_anchor_b: Vec2,
+        rest_length: f32,
+        stiffness: Stiffness,
+        damping_ratio: f32,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            rest_length,
+            stiffness,
+            damping_ratio,
+            fixed_velocities: (None, None),
+            to_anchor_a: Vec2::default(),
+            to_anchor_b: Vec2::default(),
+            axis: Vec2::default(),
+            stretch: 0.,
+            effective_mass: 0.,
+            softness: Softness::rigid(0.),
+            impulse: 0.,
+        }
+    }
+}
+
+impl Constraint for SpringJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        self.to_anchor_a = anchor_a - body_a.center_of_gravity();
+        self.to_anchor_b = anchor_b - body_b.center_of_gravity();
+
+        let delta = anchor_b - anchor_a;
+        let length = delta.magnitude();
+        self.axis = if length > f32::EPSILON {
+            delta / length
+        } else {
+            Vec2::default()
+        };
+        self.stretch = length - self.rest_length;
+
+        let cross_a = cross(self.to_anchor_a, self.axis);
+        let cross_b = cross(self.to_anchor_b, self.axis);
+        let inv_mass = body_a.inv_mass()
+            + body_a.inv_inertia() * cross_a * cross_a
+            + body_b.inv_mass()
+            + body_b.inv_inertia() * cross_b * cross_b;
+
+        self.effective_mass = if inv_mass > 0. { 1. / inv_mass } else { 0. };
+
+        // A spring constant is turned into the frequency it has with the mass it is moving
+        let frequency = match self.stiffness {
+            Stiffness::Frequency(frequency) => frequency,
+            Stiffness::Constant(constant) => {
+                (constant * inv_mass).sqrt() / (2. * std::f32::consts::PI)
+            }
+        };
+
+        // Without stiffness there is nothing to spring back, and the damping ratio makes the
+        // damping zero as well. `Softness` would turn a frequency of zero into a rigid rod instead.
+        if frequency <= 0. {
+            self.effective_mass = 0.;
+            self.impulse = 0.;
+            return;
+        }
+
+        self.softness = Softness::new(frequency, self.damping_ratio, inv_dt);
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        let impulse = self.impulse * self.axis;
+        body_a.apply_impulse_at(-impulse, self.to_anchor_a);
+        body_b.apply_impulse_at(impulse, self.to_anchor_b);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        let relative_velocity =
+            body_b.velocity_at(self.to_anchor_b) - body_a.velocity_at(self.to_anchor_a);
+
+        let impulse = -self.softness.mass_scale
+            * self.effective_mass
+            * (relative_velocity.dot(&self.axis) + self.softness.bias_rate * self.stretch)
+            - self.softness.impulse_scale * self.impulse;
+        self.impulse += impulse;
+
+        let impulse = impulse * self.axis;
+        body_a.apply_impulse_at(-impulse, self.to_anchor_a);
+        body_b.apply_impulse_at(impulse, self.to_anchor_b);
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor_a = self
+            .body_a
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_a);
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let delta = anchor_b - anchor_a;
+        if delta.magnitude() <= f32::EPSILON {
+            return;
+        }
+
+        let side = Vec2::new(-delta.y, delta.x).normalize() * 6.;
+
+        // Zig-zag between the anchors
+        let coil_count = 10;
+        let points: Vec<Vec2> = (0..=coil_count)
+            .map(|i| {
+                let offset = if i == 0 || i == coil_count {
+                    Vec2::default()
+                } else if i % 2 == 0 {
+                    side
+                } else {
+                    -side
+                };
+
+                anchor_a + delta * (i as f32 / coil_count as f32) + offset
+            })
+            .collect();
+
+        shapes.push(DebugShape::LineStrip {
+            points,
+            color: color::get(ColorName::Green),
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        self.impulse * inv_dt * self.axis
+    }
+
+    fn reaction_torque(&self, _inv_dt: f32) -> f32 {
+        0.
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::body::Body;
+
+    #[test]
+    fn springs_without_stiffness_do_nothing() {
+        for stiffness in [Stiffness::Frequency(0.), Stiffness::Constant(0.)] {
+            let a = Body::test_square(Vec2::zeros()).into_shared();
+            let b = Body::test_square(Vec2::new(20., 0.)).into_shared();
+            b.write().unwrap().apply_impulse(Vec2::new(5., 0.));
+
+            let mut spring = SpringJoint::new(
+                a.clone(),
+                Vec2::zeros(),
+                b.clone(),
+                Vec2::zeros(),
+                10.,
+                stiffness,
+                0.7,
+            );
+            spring.pre_solve(60.);
+            spring.solve();
+
+            assert_eq!(a.read().unwrap().velocity(), Vec2::zeros());
+            assert_eq!(b.read().unwrap().velocity(), Vec2::new(5., 0.));
+            assert_eq!(spring.reaction_force(60.), Vec2::zeros());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot connect a body to itself")]
+    fn joints_cannot_connect_a_body_to_itself() {
+        let body = Body::test_square(Vec2::zeros()).into_shared();
+
+        SpringJoint::new(
+            body.clone(),
+            Vec2::zeros(),
+            body,
+            Vec2::new(1., 0.),
+            10.,
+            Stiffness::Constant(1.),
+            0.7,
+        );
+    }
+}
@@ -0,0 +1,156 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::SharedBody,
+    color,
+    constraints::{Constraint, Softness},
+    debug_draw::DebugShape,
+    math::solve_symmetric_2x2,
+};
+
+/// Pulls a point on a body towards a target in world space, like a spring with a limited force.
+/// This is what the viewer uses to drag bodies around with the mouse.
+pub struct TargetJoint {
+    body: SharedBody,
+    local_anchor: Vec2,
+    target: Vec2,
+
+    max_force: f32,
+    frequency: f32,
+    damping_ratio: f32,
+
+    // Solver data (recomputed in pre_solve)
+    to_anchor: Vec2,
+    position_error: Vec2,
+    softness: Softness,
+    max_impulse: f32,
+
+    // Accumulated over frames for warm starting
+    impulse: Vec2,
+}
+
+impl TargetJoint {
+    /// The body is grabbed at `point` (in world space), which starts out as the target.
+    pub fn new(
+        body: SharedBody,
+        point: Vec2,
+        max_force: f32,
+        frequency: f32,
+        damping_ratio: f32,
+    ) -> Self {
+        let local_anchor = body
+            .read()
+            .expect("Body lock was poisoned.")
+            .world_to_local(point);
+
+        Self {
+            body,
+            local_anchor,
+            target: point,
+            max_force,
+            frequency,
+            damping_ratio,
+            to_anchor: Vec2::default(),
+            position_error: Vec2::default(),
+            softness: Softness::rigid(0.),
+            max_impulse: 0.,
+            impulse: Vec2::default(),
+        }
+    }
+
+    pub fn set_target(&mut self, target: Vec2) {
+        self.target = target;
+    }
+}
+
+impl Constraint for TargetJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.softness = Softness::new(self.frequency, self.damping_ratio, inv_dt);
+        self.max_impulse = self.max_force / inv_dt;
+
+        let mut body = self.body.write().expect("Body lock was poisoned.");
+
+        let anchor = body.local_to_world(self.local_anchor);
+        self.to_anchor = anchor - body.center_of_gravity();
+        self.position_error = anchor - self.target;
+
+        // Warm starting
+        body.apply_impulse_at(self.impulse, self.to_anchor);
+    }
+
+    fn solve(&mut self) {
+        let mut body = self.body.write().expect("Body lock was poisoned.");
+
+        let r = self.to_anchor;
+        let (m, i) = (body.inv_mass(), body.inv_inertia());
+
+        let k11 = m + r.y * r.y * i;
+        let k12 = -r.y * r.x * i;
+        let k22 = m + r.x * r.x * i;
+
+        let velocity = body.velocity_at(r);
+        let rigid_impulse = solve_symmetric_2x2(
+            k11,
+            k12,
+            k22,
+            -(velocity + self.softness.bias_rate * self.position_error),
+        );
+        let impulse =
+            self.softness.mass_scale * rigid_impulse - self.softness.impulse_scale * self.impulse;
+
+        // The force on the body is limited, so a heavy body lags behind the target
+        let previous = self.impulse;
+        self.impulse += impulse;
+        if self.impulse.magnitude() > self.max_impulse {
+            self.impulse *= self.max_impulse / self.impulse.magnitude();
+        }
+
+        body.apply_impulse_at(self.impulse - previous, r);
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor = self
+            .body
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor);
+
+        let color = color::get(ColorName::Sky);
+
+        shapes.push(DebugShape::Line {
+            start: anchor,
+            end: self.target,
+            thickness: 2.,
+            color,
+        });
+        shapes.push(DebugShape::Circle {
+            center: anchor,
+            radius: 4.,
+            color,
+        });
+        shapes.push(DebugShape::CircleLines {
+            center: self.target,
+            radius: 6.,
+            color,
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        self.impulse * inv_dt
+    }
+
+    fn reaction_torque(&self, _inv_dt: f32) -> f32 {
+        0.
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    constraints::Motor,
+    wheel_joint::WheelJoint,
+    world::{JointHandle, World},
+};
+
+/// A wheel of a vehicle, only driven wheels are turned by the throttle.
+#[derive(Debug, Clone, Copy)]
+pub struct Wheel {
+    pub joint: JointHandle,
+    pub driven: bool,
+}
+
+/// The wheel joints of a chassis, driven through `drive` instead of pushing the chassis around.
+#[derive(Debug, Clone)]
+pub struct Vehicle {
+    wheels: Vec<Wheel>,
+    /// Angular speed of the wheels at full throttle
+    max_speed: f32,
+    /// Torque the motor of each driven wheel can apply
+    max_torque: f32,
+    /// Torque each wheel brakes with at full brake
+    brake_torque: f32,
+}
+
+impl Vehicle {
+    pub fn new(wheels: Vec<Wheel>, max_speed: f32, max_torque: f32, brake_torque: f32) -> Self {
+        Self {
+            wheels,
+            max_speed,
+            max_torque,
+            brake_torque,
+        }
+    }
+
+    /// `throttle` goes from -1 (full reverse) to 1 and `brake` from 0 to 1. Braking takes
+    /// precedence over the throttle, and with neither the wheels roll freely.
+    pub fn drive(&self, world: &mut World, throttle: f32, brake: f32) {
+        let throttle = throttle.clamp(-1., 1.);
+        let brake = brake.clamp(0., 1.);
+
+        for wheel in self.wheels.iter() {
+            let motor = if brake > 0. {
+                Some(Motor {
+                    speed: 0.,
+                    max_force: brake * self.brake_torque,
+                })
+            } else if throttle != 0. && wheel.driven {
+                Some(Motor {
+                    speed: throttle * self.max_speed,
+                    max_force: self.max_torque,
+                })
+            } else {
+                None
+            };
+
+            if let Some(joint) = world.joint_mut::<WheelJoint>(wheel.joint) {
+                joint.set_motor(motor);
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{Constraint, Softness},
+    debug_draw::DebugShape,
+    math::solve_symmetric_2x2,
+};
+
+/// Locks the relative position and angle of two bodies. With a frequency of zero the joint is
+/// rigid, otherwise it springs back towards the welded pose.
+pub struct WeldJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    reference_angle: f32,
+
+    frequency: f32,
+    damping_ratio: f32,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    to_anchor_a: Vec2,
+    to_anchor_b: Vec2,
+    position_error: Vec2,
+    angle_error: f32,
+    axial_mass: f32,
+    softness: Softness,
+
+    // Accumulated over frames for warm starting
+    linear_impulse: Vec2,
+    angular_impulse: f32,
+}
+
+impl WeldJoint {
+    /// `anchor` is in world space, the bodies are welded in the pose they are currently in.
+    pub fn new(
+        body_a: SharedBody,
+        body_b: SharedBody,
+        anchor: Vec2,
+        frequency: f32,
+        damping_ratio: f32,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+
+        let (local_anchor_a, local_anchor_b, reference_angle) = {
+            let a = body_a.read().expect("Body lock was poisoned.");
+            let b = body_b.read().expect("Body lock was poisoned.");
+
+            (
+                a.world_to_local(anchor),
+                b.world_to_local(anchor),
+                b.rotation - a.rotation,
+            )
+        };
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            reference_angle,
+            frequency,
+            damping_ratio,
+            fixed_velocities: (None, None),
+            to_anchor_a: Vec2::default(),
+            to_anchor_b: Vec2::default(),
+            position_error: Vec2::default(),
+            angle_error: 0.,
+            axial_mass: 0.,
+            softness: Softness::rigid(0.),
+            linear_impulse: Vec2::default(),
+            angular_impulse: 0.,
+        }
+    }
+}
+
+impl Constraint for WeldJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.softness = Softness::new(self.frequency, self.damping_ratio, inv_dt);
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        self.to_anchor_a = anchor_a - body_a.center_of_gravity();
+        self.to_anchor_b = anchor_b - body_b.center_of_gravity();
+        self.position_error = anchor_b - anchor_a;
+        self.angle_error = body_b.rotation - body_a.rotation - self.reference_angle;
+
+        let inv_inertia = body_a.inv_inertia() + body_b.inv_inertia();
+        self.axial_mass = if inv_inertia > 0. {
+            1. / inv_inertia
+        } else {
+            0.
+        };
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        body_a.apply_impulse_at(-self.linear_impulse, self.to_anchor_a);
+        body_a.apply_angular_impulse(-self.angular_impulse);
+
+        body_b.apply_impulse_at(self.linear_impulse, self.to_anchor_b);
+        body_b.apply_angular_impulse(self.angular_impulse);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        let softness = self.softness;
+
+        // Angular
+        {
+            let relative_speed = body_b.angular_velocity() - body_a.angular_velocity();
+            let impulse = -softness.mass_scale
+                * self.axial_mass
+                * (relative_speed + softness.bias_rate * self.angle_error)
+                - softness.impulse_scale * self.angular_impulse;
+            self.angular_impulse += impulse;
+
+            body_a.apply_angular_impulse(-impulse);
+            body_b.apply_angular_impulse(impulse);
+        }
+
+        // Linear
+        {
+            let relative_velocity =
+                body_b.velocity_at(self.to_anchor_b) - body_a.velocity_at(self.to_anchor_a);
+
+            let (r_a, r_b) = (self.to_anchor_a, self.to_anchor_b);
+            let (m_a, m_b) = (body_a.inv_mass(), body_b.inv_mass());
+            let (i_a, i_b) = (body_a.inv_inertia(), body_b.inv_inertia());
+
+            let k11 = m_a + m_b + r_a.y * r_a.y * i_a + r_b.y * r_b.y * i_b;
+            let k12 = -r_a.y * r_a.x * i_a - r_b.y * r_b.x * i_b;
+            let k22 = m_a + m_b + r_a.x * r_a.x * i_a + r_b.x * r_b.x * i_b;
+
+            let rigid_impulse = solve_symmetric_2x2(
+                k11,
+                k12,
+                k22,
+                -(relative_velocity + softness.bias_rate * self.position_error),
+            );
+            let impulse =
+                softness.mass_scale * rigid_impulse - softness.impulse_scale * self.linear_impulse;
+            self.linear_impulse += impulse;
+
+            body_a.apply_impulse_at(-impulse, r_a);
+            body_b.apply_impulse_at(impulse, r_b);
+        }
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor_a = self
+            .body_a
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_a);
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let color = color::get(ColorName::Red);
+
+        shapes.push(DebugShape::Line {
+            start: anchor_a,
+            end: anchor_b,
+            thickness: 2.,
+            color,
+        });
+        shapes.push(DebugShape::SquareLines {
+            center: anchor_a,
+            size: 8.,
+            color,
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        self.linear_impulse * inv_dt
+    }
+
+    fn reaction_torque(&self, inv_dt: f32) -> f32 {
+        self.angular_impulse * inv_dt
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
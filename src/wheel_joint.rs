@@ -0,0 +1,245 @@
+use std::any::Any;
+
+use catppuccin::ColorName;
+use nalgebra_glm::Vec2;
+
+use crate::{
+    body::{self, SharedBody, SolverBody, Velocity},
+    color,
+    constraints::{self, Axis, Constraint, Motor, Softness, BIAS_FACTOR},
+    debug_draw::DebugShape,
+    math::rotate,
+};
+
+/// Attaches a wheel (body B) to a chassis (body A). The wheel can move along a suspension axis
+/// fixed to the chassis, where a spring pulls it back to the anchor, and spins freely or is
+/// driven by a motor.
+pub struct WheelJoint {
+    body_a: SharedBody,
+    body_b: SharedBody,
+
+    local_anchor_a: Vec2,
+    local_anchor_b: Vec2,
+    /// Unit suspension axis in the rotated (but not scaled) frame of body A
+    local_axis: Vec2,
+
+    frequency: f32,
+    damping_ratio: f32,
+    motor: Option<Motor>,
+
+    // Solver data (recomputed in pre_solve)
+    /// Velocities of body A and B, for the ones the solver never moves
+    fixed_velocities: (Option<Velocity>, Option<Velocity>),
+    axis: Axis,
+    perpendicular: Axis,
+    translation: f32,
+    perpendicular_error: f32,
+    axial_mass: f32,
+    perpendicular_mass: f32,
+    motor_mass: f32,
+    softness: Softness,
+
+    // Accumulated over frames for warm starting
+    perpendicular_impulse: f32,
+    spring_impulse: f32,
+    motor_impulse: f32,
+
+    inv_dt: f32,
+}
+
+impl WheelJoint {
+    /// `anchor` and `axis` are in world space, the anchor is usually the center of the wheel.
+    /// The axis does not have to be normalized but cannot be zero.
+    pub fn new(
+        body_a: SharedBody,
+        body_b: SharedBody,
+        anchor: Vec2,
+        axis: Vec2,
+        frequency: f32,
+        damping_ratio: f32,
+        motor: Option<Motor>,
+    ) -> Self {
+        body::assert_distinct(&body_a, &body_b);
+        assert!(
+            axis.magnitude_squared() > 0.,
+            "Wheel joints need a nonzero axis."
+        );
+
+        let (local_anchor_a, local_anchor_b, local_axis) = {
+            let a = body_a.read().expect("Body lock was poisoned.");
+            let b = body_b.read().expect("Body lock was poisoned.");
+
+            (
+                a.world_to_local(anchor),
+                b.world_to_local(anchor),
+                rotate(axis.normalize(), -a.rotation),
+            )
+        };
+
+        Self {
+            body_a,
+            body_b,
+            local_anchor_a,
+            local_anchor_b,
+            local_axis,
+            frequency,
+            damping_ratio,
+            motor,
+            fixed_velocities: (None, None),
+            axis: Axis::default(),
+            perpendicular: Axis::default(),
+            translation: 0.,
+            perpendicular_error: 0.,
+            axial_mass: 0.,
+            perpendicular_mass: 0.,
+            motor_mass: 0.,
+            softness: Softness::rigid(0.),
+            perpendicular_impulse: 0.,
+            spring_impulse: 0.,
+            motor_impulse: 0.,
+            inv_dt: 0.,
+        }
+    }
+
+    /// `None` lets the wheel spin freely. A motor with a speed of zero acts as a brake.
+    pub fn set_motor(&mut self, motor: Option<Motor>) {
+        self.motor = motor;
+    }
+}
+
+impl Constraint for WheelJoint {
+    fn pre_solve(&mut self, inv_dt: f32) {
+        self.inv_dt = inv_dt;
+        self.softness = Softness::new(self.frequency, self.damping_ratio, inv_dt);
+
+        let (body_a, body_b) = body::read_pair(&self.body_a, &self.body_b);
+        self.fixed_velocities = (body_a.fixed_velocity(), body_b.fixed_velocity());
+
+        let anchor_a = body_a.local_to_world(self.local_anchor_a);
+        let anchor_b = body_b.local_to_world(self.local_anchor_b);
+
+        let to_anchor_a = anchor_a - body_a.center_of_gravity();
+        let to_anchor_b = anchor_b - body_b.center_of_gravity();
+        let delta = anchor_b - anchor_a;
+
+        let axis = rotate(self.local_axis, body_a.rotation);
+        let perpendicular = Vec2::new(-axis.y, axis.x);
+        self.axis = Axis::new(axis, to_anchor_a, to_anchor_b, delta);
+        self.perpendicular = Axis::new(perpendicular, to_anchor_a, to_anchor_b, delta);
+
+        self.translation = axis.dot(&delta);
+        self.perpendicular_error = perpendicular.dot(&delta);
+
+        self.axial_mass = self.axis.effective_mass(&body_a, &body_b);
+        self.perpendicular_mass = self.perpendicular.effective_mass(&body_a, &body_b);
+        self.motor_mass = constraints::effective_mass(body_a.inv_inertia() + body_b.inv_inertia());
+
+        if self.motor.is_none() {
+            self.motor_impulse = 0.;
+        }
+
+        drop((body_a, body_b));
+
+        // Warm starting
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+        self.perpendicular
+            .apply(&mut body_a, &mut body_b, self.perpendicular_impulse);
+        self.axis
+            .apply(&mut body_a, &mut body_b, self.spring_impulse);
+        body_a.apply_angular_impulse(-self.motor_impulse);
+        body_b.apply_angular_impulse(self.motor_impulse);
+    }
+
+    fn solve(&mut self) {
+        let (mut body_a, mut body_b) =
+            SolverBody::lock_pair(&self.body_a, &self.body_b, self.fixed_velocities);
+
+        // Suspension spring
+        {
+            let speed = self.axis.speed(&body_a, &body_b);
+
+            let impulse = -self.softness.mass_scale
+                * self.axial_mass
+                * (speed + self.softness.bias_rate * self.translation)
+                - self.softness.impulse_scale * self.spring_impulse;
+            self.spring_impulse += impulse;
+
+            self.axis.apply(&mut body_a, &mut body_b, impulse);
+        }
+
+        if let Some(motor) = self.motor {
+            let relative_speed = body_b.angular_velocity() - body_a.angular_velocity();
+            let impulse = -self.motor_mass * (relative_speed - motor.speed);
+
+            let max_impulse = motor.max_force / self.inv_dt;
+            let accumulated = (self.motor_impulse + impulse).clamp(-max_impulse, max_impulse);
+            let impulse = accumulated - self.motor_impulse;
+            self.motor_impulse = accumulated;
+
+            body_a.apply_angular_impulse(-impulse);
+            body_b.apply_angular_impulse(impulse);
+        }
+
+        // Keeps the wheel on the suspension axis
+        {
+            let speed = self.perpendicular.speed(&body_a, &body_b);
+            let bias = BIAS_FACTOR * self.inv_dt * self.perpendicular_error;
+
+            let impulse = -self.perpendicular_mass * (speed + bias);
+            self.perpendicular_impulse += impulse;
+
+            self.perpendicular.apply(&mut body_a, &mut body_b, impulse);
+        }
+    }
+
+    fn draw(&self, shapes: &mut Vec<DebugShape>) {
+        let anchor_a = self
+            .body_a
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_a);
+        let anchor_b = self
+            .body_b
+            .read()
+            .expect("Body lock was poisoned.")
+            .local_to_world(self.local_anchor_b);
+
+        let color = color::get(ColorName::Teal);
+
+        shapes.push(DebugShape::Line {
+            start: anchor_a,
+            end: anchor_b,
+            thickness: 2.,
+            color,
+        });
+        shapes.push(DebugShape::Circle {
+            center: anchor_a,
+            radius: 4.,
+            color,
+        });
+        shapes.push(DebugShape::CircleLines {
+            center: anchor_b,
+            radius: 6.,
+            color,
+        });
+    }
+
+    fn reaction_force(&self, inv_dt: f32) -> Vec2 {
+        (self.perpendicular_impulse * self.perpendicular.direction
+            + self.spring_impulse * self.axis.direction)
+            * inv_dt
+    }
+
+    fn reaction_torque(&self, inv_dt: f32) -> f32 {
+        self.motor_impulse * inv_dt
+    }
+
+    fn bodies(&self) -> Vec<&SharedBody> {
+        vec![&self.body_a, &self.body_b]
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,891 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use nalgebra_glm::Vec2;
+use raylib::prelude::{RaylibDrawHandle, RaylibMode2D};
+
+use crate::{
+    arena::{BodyArena, BodyHandle},
+    body::{Body, Damping, SharedBody},
+    collision_constraint::CollisionConstraint,
+    constraints::{BreakLimits, Constraint},
+    debug_draw::DebugShape,
+    events::{Contact, Event, ManifoldPoint},
+    force_generator::ForceGenerator,
+    gear_joint::{GearJoint, JointCoordinate},
+    integrator::Integrator,
+    island::Island,
+    narrowphase::{self, ColliderProxy},
+    pre_solve::{PreSolveContact, PreSolveHook},
+    prismatic_joint::PrismaticJoint,
+    revolute_joint::RevoluteJoint,
+    snapshot::{BodySnapshot, WorldSnapshot},
+};
+
+/// Stable reference to a joint (any constraint that is not a contact) in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct JointHandle(u64);
+
+/// Stable reference to a force generator in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ForceGeneratorHandle(u64);
+
+/// Everything that is simulated: the bodies and the constraints between them.
+pub struct World {
+    bodies: BodyArena,
+
+    general_constraints: BTreeMap<JointHandle, Box<dyn Constraint>>,
+    next_joint: u64,
+    collision_map: BTreeMap<(BodyHandle, BodyHandle), CollisionConstraint>,
+    /// Sensors and the bodies overlapping them, as `(sensor, visitor)`
+    sensor_overlaps: BTreeSet<(BodyHandle, BodyHandle)>,
+    /// Pairs of bodies that never collide with each other
+    collision_exceptions: HashSet<(BodyHandle, BodyHandle)>,
+    /// Bodies connected by a joint that does not let them collide, for as long as the joint exists
+    joint_exceptions: BTreeMap<JointHandle, (BodyHandle, BodyHandle)>,
+    break_limits: BTreeMap<JointHandle, BreakLimits>,
+    /// Gear joints and the two joints they couple
+    gears: BTreeMap<JointHandle, (JointHandle, JointHandle)>,
+    force_generators: BTreeMap<ForceGeneratorHandle, Box<dyn ForceGenerator>>,
+    next_force_generator: u64,
+    iteration_count: usize,
+    /// Splits every step into this many substeps with soft contacts when set
+    substep_count: Option<usize>,
+    integrator: Integrator,
+    /// Uniform gravity, scaled by the gravity scale of each body
+    gravity: Vec2,
+    /// Damping of the bodies that do not set their own
+    default_damping: Damping,
+    pre_solve_hook: Option<PreSolveHook>,
+
+    /// What happened during the last step
+    events: Vec<Event>,
+}
+
+impl World {
+    pub fn new(iteration_count: usize) -> Self {
+        Self {
+            bodies: BodyArena::default(),
+            general_constraints: BTreeMap::default(),
+            next_joint: 0,
+            collision_map: BTreeMap::default(),
+            sensor_overlaps: BTreeSet::default(),
+            collision_exceptions: HashSet::default(),
+            joint_exceptions: BTreeMap::default(),
+            break_limits: BTreeMap::default(),
+            gears: BTreeMap::default(),
+            force_generators: BTreeMap::default(),
+            next_force_generator: 0,
+            iteration_count,
+            substep_count: None,
+            integrator: Integrator::default(),
+            gravity: Vec2::default(),
+            default_damping: Damping::default(),
+            pre_solve_hook: None,
+            events: vec![],
+        }
+    }
+
+    pub fn add_body(&mut self, body: Body) -> BodyHandle {
+        self.bodies.insert(Arc::new(RwLock::new(body)))
+    }
+
+    /// Removes the body along with every constraint that acts on it. Returns `None` if the handle
+    /// is stale. Whoever still holds on to the returned body can keep using it.
+    pub fn remove_body(&mut self, handle: BodyHandle) -> Option<SharedBody> {
+        let body = self.bodies.remove(handle)?;
+
+        self.collision_map
+            .retain(|&(a, b), _| a != handle && b != handle);
+        // No end event is sent, the body is simply gone
+        self.sensor_overlaps
+            .retain(|&(a, b)| a != handle && b != handle);
+        self.collision_exceptions
+            .retain(|&(a, b)| a != handle && b != handle);
+
+        self.general_constraints.retain(|_, constraint| {
+            !constraint
+                .bodies()
+                .into_iter()
+                .any(|other| Arc::ptr_eq(other, &body))
+        });
+        self.break_limits
+            .retain(|joint, _| self.general_constraints.contains_key(joint));
+        self.joint_exceptions
+            .retain(|joint, _| self.general_constraints.contains_key(joint));
+        // Gears act on the bodies of their joints, so they went along with them
+        self.gears
+            .retain(|gear, _| self.general_constraints.contains_key(gear));
+
+        Some(body)
+    }
+
+    /// The body itself, for constraints that need to hold on to it.
+    pub fn shared_body(&self, handle: BodyHandle) -> Option<SharedBody> {
+        self.bodies.get(handle).cloned()
+    }
+
+    pub fn add_constraint(&mut self, constraint: Box<dyn Constraint>) -> JointHandle {
+        let handle = JointHandle(self.next_joint);
+        self.next_joint += 1;
+
+        self.general_constraints.insert(handle, constraint);
+        handle
+    }
+
+    /// Removes the joint along with the gears coupling it, which would otherwise keep coupling its
+    /// bodies.
+    pub fn remove_constraint(&mut self, handle: JointHandle) -> Option<Box<dyn Constraint>> {
+        let gears: Vec<JointHandle> = self
+            .gears
+            .iter()
+            .filter(|&(_, &(a, b))| a == handle || b == handle)
+            .map(|(&gear, _)| gear)
+            .collect();
+        for gear in gears {
+            self.remove_constraint(gear);
+        }
+
+        self.break_limits.remove(&handle);
+        self.joint_exceptions.remove(&handle);
+        self.gears.remove(&handle);
+        self.general_constraints.remove(&handle)
+    }
+
+    /// Couples two revolute or prismatic joints with a gear joint, see `GearJoint`. Returns `None`
+    /// if either handle is stale or refers to a different kind of joint.
+    pub fn add_gear(
+        &mut self,
+        joint_a: JointHandle,
+        joint_b: JointHandle,
+        ratio: f32,
+    ) -> Option<JointHandle> {
+        let coordinate_a = self.joint_coordinate(joint_a)?;
+        let coordinate_b = self.joint_coordinate(joint_b)?;
+
+        let gear = self.add_constraint(Box::new(GearJoint::new(coordinate_a, coordinate_b, ratio)));
+        self.gears.insert(gear, (joint_a, joint_b));
+
+        Some(gear)
+    }
+
+    fn joint_coordinate(&mut self, joint: JointHandle) -> Option<JointCoordinate> {
+        if let Some(revolute) = self.joint_mut::<RevoluteJoint>(joint) {
+            return Some(revolute.coordinate());
+        }
+
+        self.joint_mut::<PrismaticJoint>(joint)
+            .map(|prismatic| prismatic.coordinate())
+    }
+
+    /// Makes the joint break once its reaction force or torque goes past the limits.
+    pub fn set_break_limits(&mut self, handle: JointHandle, limits: BreakLimits) {
+        if self.general_constraints.contains_key(&handle) {
+            self.break_limits.insert(handle, limits);
+        }
+    }
+
+    pub fn add_force_generator(
+        &mut self,
+        generator: Box<dyn ForceGenerator>,
+    ) -> ForceGeneratorHandle {
+        let handle = ForceGeneratorHandle(self.next_force_generator);
+        self.next_force_generator += 1;
+
+        self.force_generators.insert(handle, generator);
+        handle
+    }
+
+    /// Takes a generator out of the world, for generators like wind that never finish on their
+    /// own.
+    pub fn remove_force_generator(
+        &mut self,
+        handle: ForceGeneratorHandle,
+    ) -> Option<Box<dyn ForceGenerator>> {
+        self.force_generators.remove(&handle)
+    }
+
+    /// Solves every step in `substep_count` substeps, like the soft step solver of Box2D v3. Each
+    /// substep integrates the velocities, solves with a bias from the current positions,
+    /// integrates the positions and then relaxes the bias away. Tall stacks and bodies of very
+    /// different masses hold up a lot better.
+    ///
+    /// Every substep solves and relaxes the constraints once, the iteration count of the world
+    /// is not used. Sub-stepped worlds always integrate with symplectic Euler. The impulses of
+    /// contact events and reaction forces are those of the last substep.
+    pub fn set_substep_count(&mut self, substep_count: Option<usize>) {
+        self.substep_count = substep_count.filter(|&count| count > 0);
+    }
+
+    pub fn set_integrator(&mut self, integrator: Integrator) {
+        self.integrator = integrator;
+    }
+
+    pub fn set_gravity(&mut self, gravity: Vec2) {
+        self.gravity = gravity;
+    }
+
+    pub fn set_default_damping(&mut self, damping: Damping) {
+        self.default_damping = damping;
+    }
+
+    /// Lets `hook` change every touching pair before it is solved, replacing any previous hook.
+    pub fn set_pre_solve_hook(&mut self, hook: PreSolveHook) {
+        self.pre_solve_hook = Some(hook);
+    }
+
+    /// Events from the last step, they are cleared when the next one starts.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// The joint as its concrete type. Returns `None` if the handle is stale or the joint is of a
+    /// different type.
+    pub fn joint_mut<T: Constraint + 'static>(&mut self, handle: JointHandle) -> Option<&mut T> {
+        self.general_constraints
+            .get_mut(&handle)?
+            .as_any_mut()
+            .downcast_mut()
+    }
+
+    /// Stops the two bodies from colliding with each other.
+    pub fn disable_collision(&mut self, a: BodyHandle, b: BodyHandle) {
+        self.collision_exceptions.insert((a.min(b), a.max(b)));
+    }
+
+    /// Whether the first two bodies a joint acts on can collide with each other. They do unless
+    /// this is turned off, in which case they collide again once the joint is removed.
+    pub fn set_collide_connected(&mut self, joint: JointHandle, collide_connected: bool) {
+        if collide_connected {
+            self.joint_exceptions.remove(&joint);
+            return;
+        }
+
+        let Some(constraint) = self.general_constraints.get(&joint) else {
+            return;
+        };
+
+        let handles: Vec<BodyHandle> = constraint
+            .bodies()
+            .into_iter()
+            .take(2)
+            .filter_map(|body| self.handle_of(body))
+            .collect();
+
+        if let [a, b] = handles[..] {
+            self.joint_exceptions.insert(joint, (a.min(b), a.max(b)));
+        }
+    }
+
+    fn handle_of(&self, body: &SharedBody) -> Option<BodyHandle> {
+        self.bodies
+            .iter()
+            .find(|(_, other)| Arc::ptr_eq(other, body))
+            .map(|(handle, _)| handle)
+    }
+
+    /// The topmost dynamic body under `point`.
+    pub fn body_at(&self, point: Vec2) -> Option<BodyHandle> {
+        self.bodies
+            .iter()
+            .filter(|(_, body)| {
+                let body = body.read().expect("Body lock was poisoned.");
+                body.is_dynamic()
+                    && body
+                        .collider_in_world()
+                        .is_some_and(|collider| collider.contains_point(point))
+            })
+            .map(|(handle, _)| handle)
+            .last()
+    }
+
+    pub fn get(&self, handle: BodyHandle) -> Option<RwLockReadGuard<Body>> {
+        self.bodies
+            .get(handle)
+            .map(|body| body.read().expect("Body lock was poisoned."))
+    }
+
+    pub fn get_mut(&mut self, handle: BodyHandle) -> Option<RwLockWriteGuard<Body>> {
+        self.bodies
+            .get(handle)
+            .map(|body| body.write().expect("Body lock was poisoned."))
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        self.events.clear();
+
+        let previous_contacts: BTreeSet<(BodyHandle, BodyHandle)> =
+            self.collision_map.keys().copied().collect();
+
+        // Forces applied from outside, integrators that evaluate the forces more than once have
+        // to apply them again along with the generators
+        let applied_forces: Vec<(Vec2, f32)> = self
+            .bodies
+            .iter()
+            .map(|(_, body)| {
+                body.read()
+                    .expect("Body lock was poisoned.")
+                    .applied_forces()
+            })
+            .collect();
+
+        self.apply_force_generators(dt);
+        self.check_collisions(dt);
+
+        match self.substep_count {
+            Some(substep_count) => {
+                let substep = dt / substep_count as f32;
+
+                self.solve_substeps(substep, substep_count);
+                self.contact_events(&previous_contacts);
+                self.break_joints(substep);
+            }
+            None => {
+                self.resolve_collisions(dt);
+                self.contact_events(&previous_contacts);
+                self.break_joints(dt);
+                self.integrate(dt, &applied_forces);
+            }
+        }
+    }
+
+    fn apply_force_generators(&mut self, dt: f32) {
+        let bodies: Vec<(BodyHandle, &SharedBody)> = self.bodies.iter().collect();
+
+        for generator in self.force_generators.values_mut() {
+            generator.apply(&bodies, dt, self.gravity);
+        }
+
+        self.force_generators
+            .retain(|_, generator| !generator.is_finished());
+    }
+
+    fn check_collisions(&mut self, dt: f32) {
+        let mut handles: Vec<BodyHandle> = vec![];
+        let mut proxies: Vec<Option<ColliderProxy>> = vec![];
+        let mut is_dynamic: Vec<bool> = vec![];
+
+        for (handle, body) in self.bodies.iter() {
+            let body = body.read().expect("Body lock was poisoned.");
+
+            handles.push(handle);
+            proxies.push(
+                body.collider_in_world()
+                    .map(|collider| ColliderProxy::new(collider, body.filter(), body.is_sensor())),
+            );
+            is_dynamic.push(body.is_dynamic());
+        }
+
+        let joint_exceptions: HashSet<(BodyHandle, BodyHandle)> =
+            self.joint_exceptions.values().copied().collect();
+
+        let mut pairs = narrowphase::find_pairs(&proxies);
+        pairs.retain(|&(i, j)| {
+            let key = (handles[i], handles[j]);
+            !self.collision_exceptions.contains(&key) && !joint_exceptions.contains(&key)
+        });
+
+        // Sensors report every body passing through them, kinematic and static ones included
+        let is_sensor = |i: usize| proxies[i].as_ref().is_some_and(ColliderProxy::is_sensor);
+        let (sensor_pairs, mut pairs): (Vec<_>, Vec<_>) = pairs
+            .into_iter()
+            .partition(|&(i, j)| is_sensor(i) || is_sensor(j));
+
+        // Static and kinematic bodies cannot push each other, so there is nothing to solve
+        pairs.retain(|&(i, j)| is_dynamic[i] || is_dynamic[j]);
+
+        self.update_sensors(&handles, &proxies, &sensor_pairs, dt);
+
+        let collisions = narrowphase::collide_pairs(&proxies, &pairs, dt);
+
+        // Merging happens in pair order, so the contact map is the same regardless of how the
+        // narrowphase was split between threads. Pairs that stopped touching are dropped.
+        let mut collision_map = BTreeMap::new();
+
+        for (&(i, j), collision) in pairs.iter().zip(collisions) {
+            let Some(collision) = collision else {
+                continue;
+            };
+
+            let key = (handles[i], handles[j]);
+            let body = self
+                .bodies
+                .get(key.0)
+                .expect("Handles come from the arena.");
+            let other_body = self
+                .bodies
+                .get(key.1)
+                .expect("Handles come from the arena.");
+
+            let (incident_body, reference_body) = if collision.first_is_reference {
+                (other_body, body)
+            } else {
+                (body, other_body)
+            };
+
+            let constraint = match self.collision_map.remove(&key) {
+                // Only warm start if the bodies kept their roles
+                Some(mut constraint) if constraint.has_incident(incident_body) => {
+                    constraint.update_manifold(collision.manifold);
+                    constraint
+                }
+                _ => CollisionConstraint::new(
+                    collision.manifold,
+                    incident_body.clone(),
+                    reference_body.clone(),
+                ),
+            };
+
+            collision_map.insert(key, constraint);
+        }
+
+        if let Some(hook) = self.pre_solve_hook.as_mut() {
+            for (&(a, b), constraint) in collision_map.iter_mut() {
+                let a_is_incident = self
+                    .bodies
+                    .get(a)
+                    .is_some_and(|body| constraint.has_incident(body));
+
+                hook(&mut PreSolveContact::new(a, b, a_is_incident, constraint));
+            }
+        }
+
+        self.collision_map = collision_map;
+    }
+
+    /// Sends begin and end events for the sensor pairs whose overlap changed since the last step.
+    fn update_sensors(
+        &mut self,
+        handles: &[BodyHandle],
+        proxies: &[Option<ColliderProxy>],
+        pairs: &[(usize, usize)],
+        dt: f32,
+    ) {
+        let touching = narrowphase::touching_pairs(proxies, pairs, dt);
+
+        let overlaps: BTreeSet<(BodyHandle, BodyHandle)> = pairs
+            .iter()
+            .zip(touching)
+            .filter(|(_, touching)| *touching)
+            .map(|(&(i, j), _)| {
+                let sensor_is_first = proxies[i].as_ref().is_some_and(ColliderProxy::is_sensor);
+
+                if sensor_is_first {
+                    (handles[i], handles[j])
+                } else {
+                    (handles[j], handles[i])
+                }
+            })
+            .collect();
+
+        for &(sensor, visitor) in overlaps.difference(&self.sensor_overlaps) {
+            self.events.push(Event::SensorBegin { sensor, visitor });
+        }
+
+        for &(sensor, visitor) in self.sensor_overlaps.difference(&overlaps) {
+            self.events.push(Event::SensorEnd { sensor, visitor });
+        }
+
+        self.sensor_overlaps = overlaps;
+    }
+
+    fn resolve_collisions(&mut self, dt: f32) {
+        let inv_dt = 1. / dt;
+
+        let constraints: Vec<&mut dyn Constraint> = self
+            .general_constraints
+            .values_mut()
+            .map(|constraint| &mut **constraint as &mut dyn Constraint)
+            .chain(
+                self.collision_map
+                    .values_mut()
+                    .map(|constraint| constraint as &mut dyn Constraint),
+            )
+            .collect();
+
+        let mut islands = Island::build(self.bodies.iter().map(|(_, body)| body), constraints);
+        Island::solve_parallel(&mut islands, inv_dt, self.iteration_count);
+    }
+
+    /// Solves and integrates a whole step in substeps of length `h`.
+    fn solve_substeps(&mut self, h: f32, substep_count: usize) {
+        let inv_h = 1. / h;
+
+        let constraints: Vec<&mut dyn Constraint> = self
+            .general_constraints
+            .values_mut()
+            .map(|constraint| &mut **constraint as &mut dyn Constraint)
+            .chain(
+                self.collision_map
+                    .values_mut()
+                    .map(|constraint| constraint as &mut dyn Constraint),
+            )
+            .collect();
+
+        let mut islands = Island::build(self.bodies.iter().map(|(_, body)| body), constraints);
+
+        for substep in 0..substep_count {
+            for (_, body) in self.bodies.iter() {
+                let mut body = body.write().expect("Body lock was poisoned.");
+                body.integrate_velocity(h, self.gravity, self.default_damping);
+            }
+
+            Island::run_parallel(&mut islands, |island| {
+                island.solve_substep(inv_h, substep == 0)
+            });
+
+            for (_, body) in self.bodies.iter() {
+                let mut body = body.write().expect("Body lock was poisoned.");
+                body.integrate_position(h);
+            }
+
+            Island::run_parallel(&mut islands, Island::relax);
+        }
+
+        Island::run_parallel(&mut islands, Island::apply_restitution);
+
+        for (_, body) in self.bodies.iter() {
+            body.write()
+                .expect("Body lock was poisoned.")
+                .clear_forces();
+        }
+    }
+
+    /// Reports the pairs that started, kept or stopped touching. This runs after the solver so
+    /// the events carry the impulses of this step.
+    fn contact_events(&mut self, previous: &BTreeSet<(BodyHandle, BodyHandle)>) {
+        for (&(body_a, body_b), constraint) in self.collision_map.iter() {
+            let a_is_incident = self
+                .bodies
+                .get(body_a)
+                .is_some_and(|body| constraint.has_incident(body));
+
+            let manifold = constraint.manifold();
+            let tangent_impulse = manifold
+                .iter()
+                .map(|contact| contact.accumulated_tangent_impulse())
+                .sum();
+            let normal = manifold
+                .first()
+                .map_or(Vec2::default(), |contact| contact.normal());
+
+            let contact = Contact {
+                body_a,
+                body_b,
+                points: manifold
+                    .iter()
+                    .map(|contact| ManifoldPoint {
+                        point: contact.point(),
+                        penetration: contact.penetration(),
+                    })
+                    .collect(),
+                // The manifold normal points from the reference body to the incident body
+                normal: if a_is_incident { -normal } else { normal },
+                normal_impulse: manifold
+                    .iter()
+                    .map(|contact| contact.accumulated_normal_impulse())
+                    .sum(),
+                tangent_impulse: if a_is_incident {
+                    -tangent_impulse
+                } else {
+                    tangent_impulse
+                },
+            };
+
+            self.events.push(if previous.contains(&(body_a, body_b)) {
+                Event::ContactPersist(contact)
+            } else {
+                Event::ContactBegin(contact)
+            });
+        }
+
+        for &(body_a, body_b) in previous.iter() {
+            if !self.collision_map.contains_key(&(body_a, body_b)) {
+                self.events.push(Event::ContactEnd { body_a, body_b });
+            }
+        }
+    }
+
+    /// Removes the joints whose reaction went past their break limits during this step.
+    fn break_joints(&mut self, dt: f32) {
+        let inv_dt = 1. / dt;
+
+        let broken: Vec<(JointHandle, Vec2, f32)> = self
+            .break_limits
+            .iter()
+            .filter_map(|(&joint, limits)| {
+                let constraint = self.general_constraints.get(&joint)?;
+                let force = constraint.reaction_force(inv_dt);
+                let torque = constraint.reaction_torque(inv_dt);
+
+                (force.magnitude() > limits.force || torque.abs() > limits.torque)
+                    .then_some((joint, force, torque))
+            })
+            .collect();
+
+        for (joint, force, torque) in broken {
+            // A gear can already be gone along with a joint it coupled
+            if self.remove_constraint(joint).is_none() {
+                continue;
+            }
+
+            self.events.push(Event::JointBroken {
+                joint,
+                force,
+                torque,
+            });
+        }
+    }
+
+    /// `applied_forces` are the forces applied from outside the world for every body, in handle
+    /// order.
+    fn integrate(&mut self, dt: f32, applied_forces: &[(Vec2, f32)]) {
+        let bodies: Vec<(BodyHandle, &SharedBody)> = self.bodies.iter().collect();
+        let mut dynamic_bodies: Vec<&SharedBody> = vec![];
+
+        for &(_, body) in bodies.iter() {
+            let mut locked = body.write().expect("Body lock was poisoned.");
+
+            if locked.is_dynamic() {
+                dynamic_bodies.push(body);
+            } else {
+                locked.integrate(dt);
+            }
+        }
+
+        let generators = &mut self.force_generators;
+        self.integrator.step(
+            &dynamic_bodies,
+            dt,
+            self.gravity,
+            self.default_damping,
+            || {
+                for (&(_, body), &(force, torque)) in bodies.iter().zip(applied_forces) {
+                    let mut body = body.write().expect("Body lock was poisoned.");
+                    if body.is_dynamic() {
+                        body.apply_force(force);
+                        body.apply_torque(torque);
+                    }
+                }
+
+                for generator in generators.values_mut() {
+                    generator.apply(&bodies, dt, self.gravity);
+                }
+            },
+        );
+    }
+
+    /// Kinetic energy plus the potential energy of uniform gravity and the force generators.
+    /// Without contacts, joints and damping it should stay the same, how much it drifts shows
+    /// how accurate the integrator is.
+    pub fn energy(&self) -> f32 {
+        let bodies: Vec<(BodyHandle, &SharedBody)> = self.bodies.iter().collect();
+
+        let body_energy: f32 = bodies
+            .iter()
+            .map(|(_, body)| {
+                let body = body.read().expect("Body lock was poisoned.");
+
+                body.kinetic_energy()
+                    - body.mass()
+                        * body.gravity_scale()
+                        * self.gravity.dot(&body.center_of_gravity())
+            })
+            .sum();
+
+        let generator_energy: f32 = self
+            .force_generators
+            .values()
+            .map(|generator| generator.potential_energy(&bodies))
+            .sum();
+
+        body_energy + generator_energy
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        let bodies = self
+            .bodies
+            .iter()
+            .map(|(_, body)| {
+                let body = body.read().expect("Body lock was poisoned.");
+
+                BodySnapshot {
+                    transform: body.get_transform(),
+                    collider: body.collider().cloned(),
+                }
+            })
+            .collect();
+
+        WorldSnapshot {
+            bodies,
+            shapes: self.debug_shapes(),
+            energy: self.energy(),
+        }
+    }
+
+    pub fn draw(&self, handle: &mut RaylibMode2D<RaylibDrawHandle>) {
+        for (_, body) in self.bodies.iter() {
+            body.read().expect("Body lock was poisoned.").draw(handle);
+        }
+
+        for shape in self.debug_shapes() {
+            shape.draw(handle);
+        }
+    }
+
+    /// Everything that is drawn on top of the bodies: force generators, joints and contacts.
+    fn debug_shapes(&self) -> Vec<DebugShape> {
+        let mut shapes = vec![];
+
+        for generator in self.force_generators.values() {
+            generator.draw(&mut shapes);
+        }
+
+        for constraint in self.general_constraints.values() {
+            constraint.draw(&mut shapes);
+        }
+
+        for constraint in self.collision_map.values() {
+            constraint.draw(&mut shapes);
+        }
+
+        shapes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{distance_joint::DistanceJoint, force_generator::Wind};
+
+    /// Two bodies ten apart, held at that distance by a joint.
+    fn jointed_pair(world: &mut World) -> (BodyHandle, BodyHandle, JointHandle) {
+        let a = world.add_body(Body::test_square(Vec2::zeros()));
+        let b = world.add_body(Body::test_square(Vec2::new(10., 0.)));
+
+        let joint = DistanceJoint::new(
+            world.shared_body(a).unwrap(),
+            Vec2::zeros(),
+            world.shared_body(b).unwrap(),
+            Vec2::zeros(),
+            10.,
+            10.,
+        );
+        let joint = world.add_constraint(Box::new(joint));
+
+        (a, b, joint)
+    }
+
+    #[test]
+    fn removing_a_body_removes_its_joints() {
+        let mut world = World::new(10);
+        let (a, b, joint) = jointed_pair(&mut world);
+
+        let removed = world.remove_body(a).unwrap();
+        // The joint was the only other thing holding on to the body
+        assert_eq!(Arc::strong_count(&removed), 1);
+        assert!(world.remove_constraint(joint).is_none());
+        assert!(world.get(a).is_none());
+        assert!(world.get(b).is_some());
+
+        // Nothing is left pointing at the removed body
+        world.step(1. / 60.);
+    }
+
+    #[test]
+    fn removing_a_joint_removes_its_gears() {
+        let mut world = World::new(10);
+        let ground = world.add_body(Body::test_square(Vec2::zeros()));
+        let mut pin = |position: Vec2| {
+            let wheel = world.add_body(Body::test_square(position));
+            let joint = RevoluteJoint::new(
+                world.shared_body(ground).unwrap(),
+                world.shared_body(wheel).unwrap(),
+                position,
+                None,
+                None,
+            );
+            world.add_constraint(Box::new(joint))
+        };
+        let (joint_a, joint_b) = (pin(Vec2::new(10., 0.)), pin(Vec2::new(20., 0.)));
+        let gear = world.add_gear(joint_a, joint_b, 1.).unwrap();
+
+        world.remove_constraint(joint_a).unwrap();
+        assert!(world.remove_constraint(gear).is_none());
+        assert!(world.joint_mut::<RevoluteJoint>(joint_b).is_some());
+    }
+
+    #[test]
+    fn removing_a_body_that_is_still_shared() {
+        let mut world = World::new(10);
+        let (a, _, _) = jointed_pair(&mut world);
+        let shared = world.shared_body(a).unwrap();
+
+        let removed = world.remove_body(a).unwrap();
+        assert!(Arc::ptr_eq(&removed, &shared));
+    }
+
+    #[test]
+    fn stale_body_handles_are_rejected() {
+        let mut world = World::new(10);
+        let stale = world.add_body(Body::test_square(Vec2::zeros()));
+        world.remove_body(stale).unwrap();
+        let reused = world.add_body(Body::test_square(Vec2::zeros()));
+
+        assert!(world.remove_body(stale).is_none());
+        assert!(world.get(stale).is_none());
+        assert!(world.get(reused).is_some());
+    }
+
+    #[test]
+    fn removed_force_generators_stop_pushing() {
+        let mut world = World::new(10);
+        let body = world.add_body(Body::test_square(Vec2::zeros()));
+        let wind = world.add_force_generator(Box::new(Wind {
+            velocity: Vec2::new(100., 0.),
+            strength: 1.,
+        }));
+
+        world.step(1. / 60.);
+        let velocity = world.get(body).unwrap().velocity();
+        assert!(velocity.x > 0.);
+
+        assert!(world.remove_force_generator(wind).is_some());
+        assert!(world.remove_force_generator(wind).is_none());
+        world.step(1. / 60.);
+        assert_eq!(world.get(body).unwrap().velocity(), velocity);
+    }
+
+    #[test]
+    fn static_sensors_notice_kinematic_bodies() {
+        let body = |body_type: &str, sensor: bool| {
+            let mut body: Body = serde_json::from_value(serde_json::json!({
+                "position": [0, 0],
+                "rotation": 0,
+                "scale": [10, 10],
+                "collider_file": "StdSquare.json",
+                "mass": 1,
+                "inertia": 1,
+                "restitution": 0,
+                "friction": 0,
+                "body_type": body_type,
+                "sensor": sensor,
+            }))
+            .unwrap();
+            body.construct_collider();
+            body
+        };
+
+        let mut world = World::new(10);
+        let checkpoint = world.add_body(body("Static", true));
+        let platform = world.add_body(body("Kinematic", false));
+        world.step(1. / 60.);
+
+        assert!(world.events().iter().any(|event| matches!(
+            event,
+            Event::SensorBegin { sensor, visitor } if *sensor == checkpoint && *visitor == platform
+        )));
+    }
+}